@@ -0,0 +1,133 @@
+#ifndef KANPYO_H
+#define KANPYO_H
+
+/* Generated by cbindgen from src/lib.rs. Run `KANPYO_UPDATE_HEADER=1 cargo test -p kanpyo-capi` to update. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Class of a token.
+typedef enum KanpyoTokenClass {
+  KANPYO_TOKEN_CLASS_KNOWN = 1,
+  KANPYO_TOKEN_CLASS_UNKNOWN = 2,
+} KanpyoTokenClass;
+
+// A dictionary.
+typedef struct KanpyoDict KanpyoDict;
+
+// A tokenizer owning a dictionary.
+typedef struct KanpyoTokenizer KanpyoTokenizer;
+
+// Tokens of an input, excluding BOS and EOS.
+typedef struct KanpyoTokens KanpyoTokens;
+
+// A token borrowed from `KanpyoTokens`, which is valid until the tokens are freed.
+typedef struct KanpyoToken {
+  // NUL-terminated surface in UTF-8.
+  const char *surface;
+  // Byte length of the surface, which is `strlen(surface)`.
+  size_t surface_len;
+  // Byte offset in the input.
+  size_t position;
+  // Char offset of the beginning in the input.
+  size_t start;
+  // Char offset of the end in the input.
+  size_t end;
+  enum KanpyoTokenClass token_class;
+  // NUL-terminated comma-separated features.
+  const char *features;
+  // Number of features, which `kanpyo_tokens_feature` takes one by one.
+  size_t feature_count;
+} KanpyoToken;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the error of the last call returning a pointer in the calling thread,
+// or `NULL` if it succeeded.
+// The message is valid until the next call of the API in the thread.
+const char *kanpyo_last_error(void);
+
+// Loads a dictionary built by kanpyo from a file.
+//
+// # Safety
+//
+// `path` must be a NUL-terminated string.
+struct KanpyoDict *kanpyo_dict_load_path(const char *path);
+
+// Loads a dictionary built by kanpyo from bytes, which are copied.
+//
+// # Safety
+//
+// `data` must point to `len` bytes.
+struct KanpyoDict *kanpyo_dict_load_bytes(const uint8_t *data, size_t len);
+
+// Frees a dictionary. `NULL` is ignored.
+//
+// # Safety
+//
+// `dict` must be returned by `kanpyo_dict_load_*` and not passed to `kanpyo_tokenizer_new`.
+void kanpyo_dict_free(struct KanpyoDict *dict);
+
+// Creates a tokenizer taking the ownership of `dict`, which must not be used or freed afterwards.
+//
+// # Safety
+//
+// `dict` must be returned by `kanpyo_dict_load_*`.
+struct KanpyoTokenizer *kanpyo_tokenizer_new(struct KanpyoDict *dict);
+
+// Frees a tokenizer and its dictionary. `NULL` is ignored.
+//
+// # Safety
+//
+// `tokenizer` must be returned by `kanpyo_tokenizer_new`.
+void kanpyo_tokenizer_free(struct KanpyoTokenizer *tokenizer);
+
+// Tokenizes `len` bytes of UTF-8 text, which doesn't need to be NUL-terminated.
+// A tokenizer may be shared by threads.
+//
+// # Safety
+//
+// `tokenizer` must be returned by `kanpyo_tokenizer_new` and `text` must point to `len` bytes.
+struct KanpyoTokens *kanpyo_tokenize(const struct KanpyoTokenizer *tokenizer,
+                                     const char *text,
+                                     size_t len);
+
+// Returns the number of tokens.
+//
+// # Safety
+//
+// `tokens` must be returned by `kanpyo_tokenize`.
+size_t kanpyo_tokens_len(const struct KanpyoTokens *tokens);
+
+// Writes the `i`-th token to `out`. Returns `false` if `i` is out of range.
+//
+// # Safety
+//
+// `tokens` must be returned by `kanpyo_tokenize` and `out` must be writable.
+bool kanpyo_tokens_get(const struct KanpyoTokens *tokens, size_t i, struct KanpyoToken *out);
+
+// Returns the `j`-th feature of the `i`-th token, or `NULL` if out of range.
+// The feature is valid until the tokens are freed.
+//
+// # Safety
+//
+// `tokens` must be returned by `kanpyo_tokenize`.
+const char *kanpyo_tokens_feature(const struct KanpyoTokens *tokens, size_t i, size_t j);
+
+// Frees tokens. `NULL` is ignored.
+//
+// # Safety
+//
+// `tokens` must be returned by `kanpyo_tokenize`.
+void kanpyo_tokens_free(struct KanpyoTokens *tokens);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* KANPYO_H */
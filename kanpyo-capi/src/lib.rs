@@ -0,0 +1,306 @@
+//! C API of Kanpyo. The header is `include/kanpyo.h`.
+//!
+//! Functions returning a pointer return `NULL` on failure, and `kanpyo_last_error` describes it.
+//! Every object created by the API must be freed by its `*_free` function.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+    ptr,
+};
+
+use kanpyo::{
+    token::{Token, TokenClass},
+    tokenizer::Tokenizer,
+};
+use kanpyo_dict::dict::Dict;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+// guard returns `NULL` instead of unwinding a panic across the FFI boundary.
+// The last error is cleared first so that it describes only the failure of this call.
+fn guard<T>(f: impl FnOnce() -> Result<*mut T, String>) -> *mut T {
+    LAST_ERROR.with(|error| *error.borrow_mut() = None);
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(ptr)) => ptr,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("panicked");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// A dictionary.
+pub struct KanpyoDict(Dict);
+
+/// A tokenizer owning a dictionary.
+pub struct KanpyoTokenizer(Tokenizer);
+
+struct TokenData {
+    token: Token,
+    surface: CString,
+    features: CString,
+    feature_list: Vec<CString>,
+}
+
+/// Tokens of an input, excluding BOS and EOS.
+pub struct KanpyoTokens(Vec<TokenData>);
+
+/// Class of a token.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KanpyoTokenClass {
+    Known = 1,
+    Unknown = 2,
+}
+
+/// A token borrowed from `KanpyoTokens`, which is valid until the tokens are freed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KanpyoToken {
+    /// NUL-terminated surface in UTF-8.
+    pub surface: *const c_char,
+    /// Byte length of the surface, which is `strlen(surface)`.
+    pub surface_len: usize,
+    /// Byte offset in the input.
+    pub position: usize,
+    /// Char offset of the beginning in the input.
+    pub start: usize,
+    /// Char offset of the end in the input.
+    pub end: usize,
+    pub token_class: KanpyoTokenClass,
+    /// NUL-terminated comma-separated features.
+    pub features: *const c_char,
+    /// Number of features, which `kanpyo_tokens_feature` takes one by one.
+    pub feature_count: usize,
+}
+
+/// Returns the message of the error of the last call returning a pointer in the calling thread,
+/// or `NULL` if it succeeded.
+/// The message is valid until the next call of the API in the thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn kanpyo_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Loads a dictionary built by kanpyo from a file.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_dict_load_path(path: *const c_char) -> *mut KanpyoDict {
+    guard(|| {
+        if path.is_null() {
+            return Err("path is NULL".to_string());
+        }
+        let path = unsafe { CStr::from_ptr(path) }
+            .to_str()
+            .map_err(|e| e.to_string())?;
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        let dict = Dict::load(&mut std::io::BufReader::new(file)).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(KanpyoDict(dict))))
+    })
+}
+
+/// Loads a dictionary built by kanpyo from bytes, which are copied.
+///
+/// # Safety
+///
+/// `data` must point to `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_dict_load_bytes(data: *const u8, len: usize) -> *mut KanpyoDict {
+    guard(|| {
+        if data.is_null() {
+            return Err("data is NULL".to_string());
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(data, len) };
+        let dict = Dict::load(&mut std::io::Cursor::new(bytes)).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(KanpyoDict(dict))))
+    })
+}
+
+/// Frees a dictionary. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `dict` must be returned by `kanpyo_dict_load_*` and not passed to `kanpyo_tokenizer_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_dict_free(dict: *mut KanpyoDict) {
+    if !dict.is_null() {
+        drop(unsafe { Box::from_raw(dict) });
+    }
+}
+
+/// Creates a tokenizer taking the ownership of `dict`, which must not be used or freed afterwards.
+///
+/// # Safety
+///
+/// `dict` must be returned by `kanpyo_dict_load_*`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokenizer_new(dict: *mut KanpyoDict) -> *mut KanpyoTokenizer {
+    guard(|| {
+        if dict.is_null() {
+            return Err("dict is NULL".to_string());
+        }
+        let KanpyoDict(dict) = *unsafe { Box::from_raw(dict) };
+        Ok(Box::into_raw(Box::new(KanpyoTokenizer(Tokenizer::new(
+            dict,
+        )))))
+    })
+}
+
+/// Frees a tokenizer and its dictionary. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `tokenizer` must be returned by `kanpyo_tokenizer_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokenizer_free(tokenizer: *mut KanpyoTokenizer) {
+    if !tokenizer.is_null() {
+        drop(unsafe { Box::from_raw(tokenizer) });
+    }
+}
+
+/// Tokenizes `len` bytes of UTF-8 text, which doesn't need to be NUL-terminated.
+/// A tokenizer may be shared by threads.
+///
+/// # Safety
+///
+/// `tokenizer` must be returned by `kanpyo_tokenizer_new` and `text` must point to `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokenize(
+    tokenizer: *const KanpyoTokenizer,
+    text: *const c_char,
+    len: usize,
+) -> *mut KanpyoTokens {
+    guard(|| {
+        if tokenizer.is_null() || (text.is_null() && len > 0) {
+            return Err("tokenizer or text is NULL".to_string());
+        }
+        let KanpyoTokenizer(tokenizer) = unsafe { &*tokenizer };
+        let bytes = if len == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(text.cast::<u8>(), len) }
+        };
+        let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let to_cstring = |s: &str| CString::new(s.replace('\0', "")).unwrap_or_default();
+        let tokens = tokenizer
+            .tokenize(text)
+            .into_iter()
+            .filter(|token| token.class != TokenClass::Dummy)
+            .map(|token| {
+                let features = token.features(&tokenizer.dict);
+                TokenData {
+                    surface: to_cstring(&token.surface),
+                    features: to_cstring(&features.join(",")),
+                    feature_list: features.iter().map(|f| to_cstring(f)).collect(),
+                    token,
+                }
+            })
+            .collect();
+        Ok(Box::into_raw(Box::new(KanpyoTokens(tokens))))
+    })
+}
+
+/// Returns the number of tokens.
+///
+/// # Safety
+///
+/// `tokens` must be returned by `kanpyo_tokenize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokens_len(tokens: *const KanpyoTokens) -> usize {
+    if tokens.is_null() {
+        return 0;
+    }
+    unsafe { &*tokens }.0.len()
+}
+
+/// Writes the `i`-th token to `out`. Returns `false` if `i` is out of range.
+///
+/// # Safety
+///
+/// `tokens` must be returned by `kanpyo_tokenize` and `out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokens_get(
+    tokens: *const KanpyoTokens,
+    i: usize,
+    out: *mut KanpyoToken,
+) -> bool {
+    if tokens.is_null() || out.is_null() {
+        return false;
+    }
+    let Some(data) = unsafe { &*tokens }.0.get(i) else {
+        return false;
+    };
+    let token = &data.token;
+    unsafe {
+        out.write(KanpyoToken {
+            surface: data.surface.as_ptr(),
+            // NULs are stripped from the surface
+            surface_len: data.surface.as_bytes().len(),
+            position: token.position,
+            start: token.start,
+            end: token.end,
+            token_class: match token.class {
+                TokenClass::Unknown => KanpyoTokenClass::Unknown,
+                _ => KanpyoTokenClass::Known,
+            },
+            features: data.features.as_ptr(),
+            feature_count: data.feature_list.len(),
+        })
+    };
+    true
+}
+
+/// Returns the `j`-th feature of the `i`-th token, or `NULL` if out of range.
+/// The feature is valid until the tokens are freed.
+///
+/// # Safety
+///
+/// `tokens` must be returned by `kanpyo_tokenize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokens_feature(
+    tokens: *const KanpyoTokens,
+    i: usize,
+    j: usize,
+) -> *const c_char {
+    if tokens.is_null() {
+        return ptr::null();
+    }
+    unsafe { &*tokens }
+        .0
+        .get(i)
+        .and_then(|data| data.feature_list.get(j))
+        .map_or(ptr::null(), |feature| feature.as_ptr())
+}
+
+/// Frees tokens. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `tokens` must be returned by `kanpyo_tokenize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kanpyo_tokens_free(tokens: *mut KanpyoTokens) {
+    if !tokens.is_null() {
+        drop(unsafe { Box::from_raw(tokens) });
+    }
+}
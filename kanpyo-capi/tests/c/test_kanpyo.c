@@ -0,0 +1,106 @@
+// A test of the C API, which takes a path of a test dictionary.
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "kanpyo.h"
+
+#define CHECK(cond)                                                        \
+  do {                                                                     \
+    if (!(cond)) {                                                         \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      exit(1);                                                             \
+    }                                                                      \
+  } while (0)
+
+static void test_tokenize(const KanpyoTokenizer *tokenizer) {
+  const char *text = "東京都猫";
+  KanpyoTokens *tokens = kanpyo_tokenize(tokenizer, text, strlen(text));
+  CHECK(tokens != NULL);
+  CHECK(kanpyo_tokens_len(tokens) == 3);
+
+  const char *surfaces[] = {"東京", "都", "猫"};
+  const size_t positions[] = {0, 6, 9};
+  const size_t starts[] = {0, 2, 3};
+  KanpyoToken token;
+  for (size_t i = 0; i < kanpyo_tokens_len(tokens); i++) {
+    CHECK(kanpyo_tokens_get(tokens, i, &token));
+    CHECK(strcmp(token.surface, surfaces[i]) == 0);
+    CHECK(token.surface_len == strlen(surfaces[i]));
+    CHECK(token.position == positions[i]);
+    CHECK(strncmp(text + token.position, token.surface, token.surface_len) == 0);
+    CHECK(token.start == starts[i]);
+    CHECK(token.end == (i + 1 < 3 ? starts[i + 1] : 4));
+  }
+  CHECK(!kanpyo_tokens_get(tokens, 3, &token));
+
+  CHECK(kanpyo_tokens_get(tokens, 0, &token));
+  CHECK(token.token_class == KANPYO_TOKEN_CLASS_KNOWN);
+  CHECK(strcmp(token.features, "名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー") == 0);
+  CHECK(token.feature_count == 9);
+  CHECK(strcmp(kanpyo_tokens_feature(tokens, 0, 7), "トウキョウ") == 0);
+  CHECK(kanpyo_tokens_feature(tokens, 0, 9) == NULL);
+
+  CHECK(kanpyo_tokens_get(tokens, 2, &token));
+  CHECK(token.token_class == KANPYO_TOKEN_CLASS_UNKNOWN);
+  kanpyo_tokens_free(tokens);
+
+  tokens = kanpyo_tokenize(tokenizer, "", 0);
+  CHECK(tokens != NULL && kanpyo_tokens_len(tokens) == 0);
+  kanpyo_tokens_free(tokens);
+
+  // NULs are stripped from surfaces
+  tokens = kanpyo_tokenize(tokenizer, "猫\0", 4);
+  CHECK(tokens != NULL && kanpyo_tokens_len(tokens) > 0);
+  CHECK(kanpyo_tokens_get(tokens, 0, &token));
+  CHECK(token.surface_len == strlen(token.surface));
+  kanpyo_tokens_free(tokens);
+
+  CHECK(kanpyo_tokenize(tokenizer, "\xff", 1) == NULL);
+  CHECK(kanpyo_last_error() != NULL);
+  // a successful call clears the last error
+  tokens = kanpyo_tokenize(tokenizer, "", 0);
+  CHECK(tokens != NULL && kanpyo_last_error() == NULL);
+  kanpyo_tokens_free(tokens);
+}
+
+static KanpyoDict *load_bytes(const char *path) {
+  FILE *f = fopen(path, "rb");
+  CHECK(f != NULL);
+  fseek(f, 0, SEEK_END);
+  long len = ftell(f);
+  fseek(f, 0, SEEK_SET);
+  unsigned char *data = malloc((size_t)len);
+  CHECK(data != NULL);
+  CHECK(fread(data, 1, (size_t)len, f) == (size_t)len);
+  fclose(f);
+  KanpyoDict *dict = kanpyo_dict_load_bytes(data, (size_t)len);
+  free(data);
+  return dict;
+}
+
+int main(int argc, char **argv) {
+  CHECK(argc == 2);
+
+  KanpyoDict *dict = kanpyo_dict_load_path(argv[1]);
+  CHECK(dict != NULL);
+  KanpyoTokenizer *tokenizer = kanpyo_tokenizer_new(dict);
+  CHECK(tokenizer != NULL);
+  test_tokenize(tokenizer);
+  kanpyo_tokenizer_free(tokenizer);
+
+  tokenizer = kanpyo_tokenizer_new(load_bytes(argv[1]));
+  CHECK(tokenizer != NULL);
+  test_tokenize(tokenizer);
+  kanpyo_tokenizer_free(tokenizer);
+
+  CHECK(kanpyo_dict_load_path("no such file") == NULL);
+  CHECK(strstr(kanpyo_last_error(), "no such file") != NULL);
+  CHECK(kanpyo_tokenizer_new(NULL) == NULL);
+  kanpyo_dict_free(NULL);
+  kanpyo_tokenizer_free(NULL);
+  kanpyo_tokens_free(NULL);
+
+  printf("ok\n");
+  return 0;
+}
@@ -0,0 +1,53 @@
+#![cfg(target_os = "linux")]
+
+use std::{fs, path::Path, process::Command};
+
+use kanpyo_dict::testutil::test_dict;
+
+// build_dict writes the test dictionary in `dir` and returns the path.
+fn build_dict(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("test.dict");
+    test_dict()
+        .build(&mut fs::File::create(&path).unwrap())
+        .unwrap();
+    path
+}
+
+#[test]
+fn test_c_api() {
+    if Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("skipped: cc is not found");
+        return;
+    }
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("kanpyo-capi-test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let dict = build_dict(&dir);
+
+    // the cdylib is built next to the test binary
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let program = dir.join("test_kanpyo");
+    let status = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(crate_dir.join("include"))
+        .arg(crate_dir.join("tests/c/test_kanpyo.c"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-lkanpyo_capi", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to compile the C test");
+
+    let output = Command::new(&program).arg(&dict).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
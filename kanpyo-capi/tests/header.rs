@@ -0,0 +1,24 @@
+use std::{fs, path::Path};
+
+#[test]
+fn test_header_is_up_to_date() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(Path::new(crate_dir).join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::Builder::new()
+        .with_crate(crate_dir)
+        .with_config(config)
+        .generate()
+        .unwrap()
+        .write(&mut generated);
+    let generated = String::from_utf8(generated).unwrap();
+    let path = Path::new(crate_dir).join("include/kanpyo.h");
+    if std::env::var_os("KANPYO_UPDATE_HEADER").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+    assert_eq!(
+        fs::read_to_string(&path).unwrap_or_default(),
+        generated,
+        "include/kanpyo.h is out of date. Run `KANPYO_UPDATE_HEADER=1 cargo test -p kanpyo-capi`."
+    );
+}
@@ -6,19 +6,24 @@ use unk::parse_unk_def;
 use crate::{
     char_category_def::CharCategoryDef,
     connection::ConnectionTable,
+    context_id::ContextIdDef,
     dict,
     error::{KanpyoError, Result},
     index,
     morph::Morphs,
-    morph_feature, unk_dict,
+    morph_feature,
+    surface::SurfaceTable,
+    unk_dict,
 };
 
 use self::{config::Config, record::parse_csv};
 
 pub mod char_def;
 pub mod config;
+pub mod id_def;
 pub mod matrix_def;
 pub mod record;
+pub mod rewrite_def;
 pub mod unk;
 
 pub struct DictionaryBuilder {}
@@ -43,12 +48,44 @@ impl DictionaryBuilder {
         Ok(csv_files)
     }
 
+    // context_id_def returns the context id definition.
+    // left-id.def, right-id.def and rewrite.def are optional.
+    fn context_id_def(config: &Config) -> Result<ContextIdDef> {
+        let path = |file_name| config.root_path.join(file_name);
+        let (left_id_def, right_id_def, rewrite_def) = (
+            path(config.left_id_def_file_name),
+            path(config.right_id_def_file_name),
+            path(config.rewrite_def_file_name),
+        );
+        let parse_id_def = |path: &std::path::Path| {
+            if path.exists() {
+                id_def::parse_id_def(path, config.encoding)
+            } else {
+                Ok(vec![])
+            }
+        };
+        let rewrite_def = if rewrite_def.exists() {
+            rewrite_def::parse_rewrite_def(&rewrite_def, config.encoding)?
+        } else {
+            Default::default()
+        };
+        Ok(ContextIdDef::new(
+            rewrite_def,
+            parse_id_def(&left_id_def)?,
+            parse_id_def(&right_id_def)?,
+        ))
+    }
+
     pub fn from_config(config: &Config) -> Result<dict::Dict> {
         let csv_files = Self::collect_csv_files(config)?;
+        let context_id_def = Self::context_id_def(config)?;
 
         let sorted_records = csv_files
             .into_iter()
-            .flat_map(|csv| parse_csv(&csv, config.encoding).expect("Failed to parse csv"))
+            .map(|csv| parse_csv(&csv, config.encoding, &context_id_def))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
             .sorted()
             .collect::<Vec<_>>();
 
@@ -84,6 +121,7 @@ impl DictionaryBuilder {
 
         // index
         let index = index::IndexTable::build(&sorted_keywords).expect("Failed to build index");
+        let surface_table = SurfaceTable::build(&sorted_keywords);
 
         // char.def
         let char_category_def = CharCategoryDef::new(
@@ -105,13 +143,16 @@ impl DictionaryBuilder {
         )
         .expect("Failed to build unk dict");
 
-        Ok(dict::Dict::new(
+        let mut dict = dict::Dict::new(
             morphs,
             morph_feature_table,
             connection_table,
             index,
             char_category_def,
             unk_dict,
-        ))
+            surface_table,
+        );
+        dict.context_id_def = context_id_def;
+        Ok(dict)
     }
 }
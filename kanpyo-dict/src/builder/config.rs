@@ -13,6 +13,12 @@ pub struct Config<'a> {
     pub char_def_file_name: &'a str,
     // 未知語定義ファイル名
     pub unk_def_file_name: &'a str,
+    // 左文脈ID定義ファイル名
+    pub left_id_def_file_name: &'a str,
+    // 右文脈ID定義ファイル名
+    pub right_id_def_file_name: &'a str,
+    // 素性書き換え定義ファイル名
+    pub rewrite_def_file_name: &'a str,
 }
 
 impl<'a> Config<'a> {
@@ -23,6 +29,9 @@ impl<'a> Config<'a> {
             matrix_def_file_name: "matrix.def",
             char_def_file_name: "char.def",
             unk_def_file_name: "unk.def",
+            left_id_def_file_name: "left-id.def",
+            right_id_def_file_name: "right-id.def",
+            rewrite_def_file_name: "rewrite.def",
         }
     }
 }
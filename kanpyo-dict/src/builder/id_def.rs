@@ -0,0 +1,46 @@
+use std::{fs, path::Path};
+
+use encoding_rs::Encoding;
+
+use crate::error::{KanpyoError, Result};
+
+// parse_id_def parses left-id.def or right-id.def.
+// Each line is a context id and the rewritten features it stands for.
+pub fn parse_id_def(path: &Path, encoding: &'static Encoding) -> Result<Vec<(i16, String)>> {
+    let byte = fs::read(path)?;
+    let (utf8, _, had_errors) = encoding.decode(&byte);
+    if had_errors {
+        return Err(KanpyoError::EncodingError);
+    }
+    parse(&utf8)
+}
+
+fn parse(text: &str) -> Result<Vec<(i16, String)>> {
+    let mut ids = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        // e.g. 0 BOS/EOS,*,*,*,*,*,*,*,BOS/EOS
+        let (id, features) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| KanpyoError::Parse(format!("invalid id definition: {}", line)))?;
+        ids.push((id.parse()?, features.trim().to_string()));
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("0 BOS/EOS,*,*,*,*,*,*,*,BOS/EOS\n1 その他,間投,*,*,*,*,*\n").unwrap(),
+            vec![
+                (0, "BOS/EOS,*,*,*,*,*,*,*,BOS/EOS".to_string()),
+                (1, "その他,間投,*,*,*,*,*".to_string()),
+            ]
+        );
+        assert!(parse("0\n").is_err());
+        assert!(parse("x 名詞\n").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use encoding_rs::Encoding;
 use std::{fs, path::Path};
 
-use crate::error::{KanpyoError, Result};
+use crate::{
+    context_id::ContextIdDef,
+    error::{KanpyoError, Result},
+};
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 /// Record represents a record in the CSV file.
 pub struct Record {
@@ -18,7 +21,13 @@ pub struct Record {
     pub user_data: Vec<String>,
 }
 
-pub fn parse_csv(path: &Path, encoding: &'static Encoding) -> Result<Vec<Record>> {
+// parse_csv parses a lexicon CSV file.
+// Empty context ids are resolved from the features by `context_id_def`.
+pub fn parse_csv(
+    path: &Path,
+    encoding: &'static Encoding,
+    context_id_def: &ContextIdDef,
+) -> Result<Vec<Record>> {
     let byte = fs::read(path)?;
     let (utf8, _, had_errors) = encoding.decode(&byte);
     if had_errors {
@@ -30,12 +39,33 @@ pub fn parse_csv(path: &Path, encoding: &'static Encoding) -> Result<Vec<Record>
     let mut records = Vec::new();
     for result in reader.records() {
         let record = result?;
+        let user_data = record
+            .iter()
+            .skip(4)
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (left_id, right_id) = (record.get(1).unwrap(), record.get(2).unwrap());
+        let (left_id, right_id) = if left_id.is_empty() || right_id.is_empty() {
+            let features = user_data.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+            let not_found = || KanpyoError::ContextIdNotFound(user_data.join(","));
+            let left_id = match left_id {
+                "" => context_id_def.left_id(&features).ok_or_else(not_found)? as usize,
+                id => id.parse()?,
+            };
+            let right_id = match right_id {
+                "" => context_id_def.right_id(&features).ok_or_else(not_found)? as usize,
+                id => id.parse()?,
+            };
+            (left_id, right_id)
+        } else {
+            (left_id.parse()?, right_id.parse()?)
+        };
         records.push(Record {
             surface: record.get(0).unwrap().to_string(),
-            left_id: record.get(1).unwrap().parse()?,
-            right_id: record.get(2).unwrap().parse()?,
+            left_id,
+            right_id,
             cost: record.get(3).unwrap().parse()?,
-            user_data: record.iter().skip(4).map(|s| s.to_string()).collect(),
+            user_data,
         });
     }
     Ok(records)
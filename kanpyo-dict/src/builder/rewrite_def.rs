@@ -0,0 +1,100 @@
+use std::{fs, path::Path};
+
+use encoding_rs::Encoding;
+
+use crate::error::{KanpyoError, Result};
+
+// Rule represents a pattern of features and its result.
+pub type Rule = (String, String);
+
+// RewriteDef represents the sections of rewrite.def.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct RewriteDef {
+    // rules for features of the CRF
+    pub unigram: Vec<Rule>,
+    // rules for the keys of left-id.def
+    pub left: Vec<Rule>,
+    // rules for the keys of right-id.def
+    pub right: Vec<Rule>,
+}
+
+pub fn parse_rewrite_def(path: &Path, encoding: &'static Encoding) -> Result<RewriteDef> {
+    let byte = fs::read(path)?;
+    let (utf8, _, had_errors) = encoding.decode(&byte);
+    if had_errors {
+        return Err(KanpyoError::EncodingError);
+    }
+    parse(&utf8)
+}
+
+fn parse(text: &str) -> Result<RewriteDef> {
+    let mut def = RewriteDef::default();
+    let mut rules = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // e.g. [left rewrite]
+        match line {
+            "[unigram rewrite]" => rules = Some(&mut def.unigram),
+            "[left rewrite]" => rules = Some(&mut def.left),
+            "[right rewrite]" => rules = Some(&mut def.right),
+            _ => {
+                let invalid =
+                    || KanpyoError::Parse(format!("rewrite.def line {}: {}", i + 1, line));
+                // e.g. 助詞,*,*,*,*,*,(の|に|を) $1,$2,$3,$4,$5,$6,$7
+                let fields = line.split_whitespace().collect::<Vec<_>>();
+                let [pattern, result] = fields[..] else {
+                    return Err(invalid());
+                };
+                rules
+                    .as_mut()
+                    .ok_or_else(invalid)?
+                    .push((pattern.to_string(), result.to_string()));
+            }
+        }
+    }
+    Ok(def)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let def = parse(
+            "\
+# comment
+[unigram rewrite]
+*,*,*,*,*,*,*  $1,$2,$3,$4,$5,$6,$7
+
+[left rewrite]
+助詞,*,*,*,*,*,(の|に|を)  $1,$2,$3,$4,$5,$6,$7
+*,*,*,*,*,*,*  $1,$2,$3,$4,$5,$6,*
+[right rewrite]
+*\t$1
+",
+        )
+        .unwrap();
+        assert_eq!(def.unigram.len(), 1);
+        assert_eq!(
+            def.left,
+            vec![
+                (
+                    "助詞,*,*,*,*,*,(の|に|を)".to_string(),
+                    "$1,$2,$3,$4,$5,$6,$7".to_string()
+                ),
+                (
+                    "*,*,*,*,*,*,*".to_string(),
+                    "$1,$2,$3,$4,$5,$6,*".to_string()
+                ),
+            ]
+        );
+        assert_eq!(def.right, vec![("*".to_string(), "$1".to_string())]);
+
+        assert!(parse("* $1\n").is_err());
+        assert!(parse("[left rewrite]\n* $1 $2\n").is_err());
+    }
+}
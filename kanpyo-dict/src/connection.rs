@@ -12,6 +12,16 @@ impl ConnectionTable {
     pub fn get(&self, row: usize, col: usize) -> i16 {
         self.data[self.row * col + row]
     }
+
+    // row returns the number of right context ids.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    // col returns the number of left context ids.
+    pub fn col(&self) -> usize {
+        self.col
+    }
 }
 
 impl From<MatrixDef> for ConnectionTable {
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use bincode::{Decode, Encode};
+
+use crate::{
+    builder::rewrite_def::{RewriteDef, Rule},
+    dict::DictReadWrite,
+};
+
+// RewriteRule rewrites features that match a pattern like rewrite.def of MeCab.
+// Each field of a pattern is `*`, alternatives like `(の|に|を)` or a literal,
+// and `$n` in a result is replaced with the n-th feature.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct RewriteRule {
+    pub pattern: Vec<String>,
+    pub result: String,
+}
+
+impl RewriteRule {
+    pub fn new(pattern: &str, result: &str) -> Self {
+        RewriteRule {
+            pattern: pattern.split(',').map(str::to_string).collect(),
+            result: result.to_string(),
+        }
+    }
+
+    fn matches(pattern: &str, feature: &str) -> bool {
+        if pattern == "*" || pattern == feature {
+            return true;
+        }
+        pattern
+            .strip_prefix('(')
+            .and_then(|p| p.strip_suffix(')'))
+            .is_some_and(|alternatives| alternatives.split('|').any(|a| a == feature))
+    }
+
+    // rewrite returns `None` if the features don't match the pattern.
+    pub fn rewrite(&self, features: &[&str]) -> Option<String> {
+        if self.pattern.len() > features.len()
+            || !self
+                .pattern
+                .iter()
+                .zip(features)
+                .all(|(pattern, feature)| Self::matches(pattern, feature))
+        {
+            return None;
+        }
+        let mut result = String::with_capacity(self.result.len());
+        let mut chars = self.result.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch != '$' {
+                result.push(ch);
+                continue;
+            }
+            let mut n = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                n.push(digit);
+            }
+            match n.parse::<usize>() {
+                Ok(n) => result.push_str(features.get(n.checked_sub(1)?)?),
+                Err(_) => result.push('$'),
+            }
+        }
+        Some(result)
+    }
+}
+
+// Rewriter applies the first matching rule of a section of rewrite.def.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct Rewriter(pub Vec<RewriteRule>);
+
+impl Rewriter {
+    pub fn rewrite(&self, features: &[&str]) -> Option<String> {
+        self.0.iter().find_map(|rule| rule.rewrite(features))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<Rule>> for Rewriter {
+    fn from(rules: Vec<Rule>) -> Self {
+        Rewriter(
+            rules
+                .iter()
+                .map(|(pattern, result)| RewriteRule::new(pattern, result))
+                .collect(),
+        )
+    }
+}
+
+// ContextIdDef resolves context ids of features with rewrite.def, left-id.def and right-id.def.
+// Features are rewritten by the left or right rules and looked up in the id definitions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct ContextIdDef {
+    pub unigram: Rewriter,
+    pub left: Rewriter,
+    pub right: Rewriter,
+    pub left_ids: BTreeMap<String, i16>,
+    pub right_ids: BTreeMap<String, i16>,
+}
+
+impl ContextIdDef {
+    pub fn new(
+        rewrite_def: RewriteDef,
+        left_id_def: Vec<(i16, String)>,
+        right_id_def: Vec<(i16, String)>,
+    ) -> Self {
+        ContextIdDef {
+            unigram: Rewriter::from(rewrite_def.unigram),
+            left: Rewriter::from(rewrite_def.left),
+            right: Rewriter::from(rewrite_def.right),
+            left_ids: left_id_def.into_iter().map(|(id, f)| (f, id)).collect(),
+            right_ids: right_id_def.into_iter().map(|(id, f)| (f, id)).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left_ids.is_empty() && self.right_ids.is_empty()
+    }
+
+    pub fn left_id(&self, features: &[&str]) -> Option<i16> {
+        self.left_ids.get(&self.left.rewrite(features)?).copied()
+    }
+
+    pub fn right_id(&self, features: &[&str]) -> Option<i16> {
+        self.right_ids.get(&self.right.rewrite(features)?).copied()
+    }
+
+    // context_ids returns the left and right context ids of features.
+    pub fn context_ids(&self, features: &[&str]) -> Option<(i16, i16)> {
+        Some((self.left_id(features)?, self.right_id(features)?))
+    }
+}
+
+impl DictReadWrite for ContextIdDef {
+    fn write_dict<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        match bincode::encode_to_vec(self, bincode::config::standard()) {
+            Ok(enc) => w.write_all(&enc),
+            Err(e) => Err(std::io::Error::other(e)),
+        }
+    }
+
+    fn from_dict<R: std::io::Read>(r: &mut R) -> std::io::Result<Self> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf)?;
+        match bincode::decode_from_slice(&buf, bincode::config::standard()) {
+            Ok(v) => Ok(v.0),
+            Err(e) => Err(std::io::Error::other(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_context_id_def() -> ContextIdDef {
+        ContextIdDef::new(
+            RewriteDef {
+                unigram: vec![("*,*".to_string(), "$1,$2".to_string())],
+                left: vec![
+                    ("助詞,*,(の|に)".to_string(), "$1,$2,$3".to_string()),
+                    ("*,*".to_string(), "$1,$2,*".to_string()),
+                ],
+                right: vec![("*".to_string(), "$1".to_string())],
+            },
+            vec![
+                (1, "名詞,一般,*".to_string()),
+                (2, "助詞,格助詞,の".to_string()),
+                (3, "助詞,格助詞,*".to_string()),
+            ],
+            vec![(4, "名詞".to_string()), (5, "助詞".to_string())],
+        )
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let rule = RewriteRule::new("名詞,(一般|固有名詞)", "$2/$1/$3$/$");
+        assert_eq!(
+            rule.rewrite(&["名詞", "一般", "人名"]),
+            Some("一般/名詞/人名$/$".to_string())
+        );
+        assert_eq!(rule.rewrite(&["名詞", "固有名詞"]), None);
+        assert_eq!(rule.rewrite(&["名詞", "接尾", "人名"]), None);
+        assert_eq!(rule.rewrite(&["名詞"]), None);
+
+        // only `*` itself is a wildcard
+        let rule = RewriteRule::new("*接頭詞", "$1");
+        assert_eq!(rule.rewrite(&["名詞"]), None);
+        assert_eq!(rule.rewrite(&["*接頭詞"]), Some("*接頭詞".to_string()));
+    }
+
+    #[test]
+    fn test_context_ids() {
+        let def = new_context_id_def();
+        assert_eq!(
+            def.context_ids(&["名詞", "一般", "かんぴょう"]),
+            Some((1, 4))
+        );
+        assert_eq!(def.context_ids(&["助詞", "格助詞", "の"]), Some((2, 5)));
+        assert_eq!(def.context_ids(&["助詞", "格助詞", "が"]), Some((3, 5)));
+        assert_eq!(def.context_ids(&["動詞", "自立"]), None);
+        assert_eq!(
+            def.unigram.rewrite(&["名詞", "一般", "かんぴょう"]),
+            Some("名詞,一般".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_write() {
+        let def = new_context_id_def();
+        let mut buf = Vec::new();
+        def.write_dict(&mut buf).unwrap();
+        assert_eq!(ContextIdDef::from_dict(&mut buf.as_slice()).unwrap(), def);
+    }
+}
@@ -1,13 +1,17 @@
 use std::io::Read;
 use std::io::Seek;
 use std::io::Write;
+use std::ops::Range;
 
 use crate::char_category_def;
 use crate::connection;
+use crate::context_id;
 use crate::error::Result;
 use crate::index;
 use crate::morph;
 use crate::morph_feature;
+use crate::surface;
+use crate::trie::da::KeywordID;
 use crate::unk_dict;
 
 pub trait DictReadWrite {
@@ -27,6 +31,37 @@ pub struct Dict {
     pub index_table: index::IndexTable,
     pub char_category_def: char_category_def::CharCategoryDef,
     pub unk_dict: unk_dict::UnkDict,
+    pub surface_table: surface::SurfaceTable,
+    // left-id.def, right-id.def and rewrite.def
+    pub context_id_def: context_id::ContextIdDef,
+}
+
+// MAXIMUM_UNKNOWN_WORD_LENGTH limits the number of characters grouped into an unknown word.
+const MAXIMUM_UNKNOWN_WORD_LENGTH: usize = 1024;
+
+// Entry represents a known word registered in the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry<'a> {
+    pub id: KeywordID,
+    pub surface: &'a str,
+    pub morph: &'a morph::Morph,
+    pub features: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryClass {
+    Known,
+    Unknown,
+}
+
+// Lookup represents a dictionary entry matched at a position of an input.
+// The surface of an entry is a slice of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup<'a> {
+    pub class: EntryClass,
+    pub byte_pos: usize,
+    pub char_pos: usize,
+    pub entry: Entry<'a>,
 }
 
 impl Dict {
@@ -37,6 +72,7 @@ impl Dict {
         index: index::IndexTable,
         char_category_def: char_category_def::CharCategoryDef,
         unk_dict: unk_dict::UnkDict,
+        surface_table: surface::SurfaceTable,
     ) -> Self {
         Dict {
             morphs,
@@ -45,9 +81,125 @@ impl Dict {
             index_table: index,
             char_category_def,
             unk_dict,
+            surface_table,
+            context_id_def: Default::default(),
         }
     }
 
+    // surface returns the surface of a known word whose morph id starts from 1.
+    // Returns `None` if the dictionary was built without surfaces.
+    pub fn surface(&self, id: KeywordID) -> Option<&str> {
+        self.surface_table.get(id)
+    }
+
+    // entries returns an iterator over all known words in the order of morph ids.
+    pub fn entries(&self) -> impl Iterator<Item = Entry<'_>> {
+        self.morphs.iter().enumerate().filter_map(|(i, morph)| {
+            let id = i as KeywordID + 1;
+            Some(Entry {
+                id,
+                surface: self.surface(id)?,
+                morph,
+                features: self.morph_feature_table.features(id).unwrap_or_default(),
+            })
+        })
+    }
+
+    // search_unknown_words returns the unknown morph ids and the byte length of an unknown word at the head of `text`.
+    // Unknown word processing is triggered either if no known words matched
+    // or if the `invoke_list` requires it for the character category of the first character.
+    pub fn search_unknown_words(
+        &self,
+        text: &str,
+        matched_known: bool,
+    ) -> Option<(Range<KeywordID>, usize)> {
+        let ch = text.chars().next()?;
+        // Determine the character category of `ch`.
+        let char_category = self.char_category_def.char_category(ch);
+        if matched_known && !self.char_category_def.invoke_list[char_category as usize] {
+            return None;
+        }
+
+        // If this category supports grouping, we can bundle consecutive characters of the same type.
+        let is_group = *self
+            .char_category_def
+            .group_list
+            .get(char_category as usize)
+            .unwrap_or(&false);
+
+        // Determine how far the unknown sequence goes.
+        let mut byte_length = ch.len_utf8();
+        let mut unknown_word_length = 1;
+
+        // Extend the unknown sequence while the next character is in the same category.
+        if is_group {
+            for next_char in text[byte_length..].chars() {
+                let next_category = self.char_category_def.char_category(next_char);
+                if next_category != char_category {
+                    break;
+                }
+                byte_length += next_char.len_utf8();
+                unknown_word_length += 1;
+
+                // Limit the maximum length of unknown sequences.
+                if unknown_word_length >= MAXIMUM_UNKNOWN_WORD_LENGTH {
+                    break;
+                }
+            }
+        }
+
+        // Get the registered (morph_id, count) for this character category in the unknown dictionary.
+        let &(morph_id, count) = self
+            .unk_dict
+            .char_category_to_morph_id
+            .get(&char_category)?;
+        Some((morph_id..morph_id + count as KeywordID, byte_length))
+    }
+
+    // lookup returns the known words starting at each position of `input`
+    // and the unknown words that would be generated there.
+    pub fn lookup<'a>(&'a self, input: &'a str) -> Vec<Lookup<'a>> {
+        let mut results = vec![];
+        for (char_pos, (byte_pos, _)) in input.char_indices().enumerate() {
+            let text = &input[byte_pos..];
+            let known = self.index_table.search_common_prefix_of(text);
+            let matched_known = known.is_some();
+            for (id, byte_length) in known.unwrap_or_default() {
+                results.push(Lookup {
+                    class: EntryClass::Known,
+                    byte_pos,
+                    char_pos,
+                    entry: Entry {
+                        id,
+                        surface: &text[..byte_length],
+                        morph: &self.morphs[id - 1],
+                        features: self.morph_feature_table.features(id).unwrap_or_default(),
+                    },
+                });
+            }
+            if let Some((ids, byte_length)) = self.search_unknown_words(text, matched_known) {
+                for id in ids {
+                    results.push(Lookup {
+                        class: EntryClass::Unknown,
+                        byte_pos,
+                        char_pos,
+                        entry: Entry {
+                            id,
+                            surface: &text[..byte_length],
+                            morph: &self.unk_dict.morphs[id - 1],
+                            features: self
+                                .unk_dict
+                                .morph_feature_table
+                                .features(id)
+                                .unwrap_or_default(),
+                        },
+                    });
+                }
+            }
+        }
+        results
+    }
+
     pub fn build<W: Write + Seek>(&self, f: &mut W) -> Result<()> {
         let mut zip = zip::ZipWriter::new(f);
         let options = zip::write::SimpleFileOptions::default()
@@ -65,6 +217,10 @@ impl Dict {
         self.char_category_def.write_dict(&mut zip)?;
         zip.start_file("unk.dict", options)?;
         self.unk_dict.write_dict(&mut zip)?;
+        zip.start_file("surface.dict", options)?;
+        self.surface_table.write_dict(&mut zip)?;
+        zip.start_file("context_id.dict", options)?;
+        self.context_id_def.write_dict(&mut zip)?;
         Ok(())
     }
     pub fn load<R: Read + Seek>(r: &mut R) -> Result<Self> {
@@ -105,14 +261,37 @@ impl Dict {
             unk_dict::UnkDict::from_dict(&mut r)?
         };
 
-        Ok(Dict::new(
+        // dictionaries built by older versions have no surfaces
+        let surface_table = match zip.by_name("surface.dict") {
+            Ok(surface_dict) => {
+                let mut r = std::io::BufReader::new(surface_dict);
+                surface::SurfaceTable::from_dict(&mut r)?
+            }
+            Err(zip::result::ZipError::FileNotFound) => surface::SurfaceTable::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        // dictionaries built by older versions have no context id definitions
+        let context_id_def = match zip.by_name("context_id.dict") {
+            Ok(context_id_dict) => {
+                let mut r = std::io::BufReader::new(context_id_dict);
+                context_id::ContextIdDef::from_dict(&mut r)?
+            }
+            Err(zip::result::ZipError::FileNotFound) => context_id::ContextIdDef::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut dict = Dict::new(
             morphs,
             morph_feature_table,
             connection_table,
             index,
             char_category_def,
             unk_dict,
-        ))
+            surface_table,
+        );
+        dict.context_id_def = context_id_def;
+        Ok(dict)
     }
 }
 
@@ -124,9 +303,8 @@ mod tests {
     use super::*;
 
     fn new_test_dict() -> Dict {
-        let index =
-            index::IndexTable::build(&["key1".to_string(), "key2".to_string(), "key3".to_string()])
-                .expect("Failed to build index table");
+        let keywords = ["key1".to_string(), "key2".to_string(), "key3".to_string()];
+        let index = index::IndexTable::build(&keywords).expect("Failed to build index table");
 
         Dict {
             morphs: morph::Morphs::from(vec![
@@ -166,6 +344,16 @@ mod tests {
                 .build(),
                 char_category_to_morph_id: vec![(1, (1, 1)), (2, (2, 2))].into_iter().collect(),
             },
+            surface_table: surface::SurfaceTable::build(&keywords),
+            context_id_def: context_id::ContextIdDef::new(
+                crate::builder::rewrite_def::RewriteDef {
+                    unigram: vec![],
+                    left: vec![("*".to_string(), "$1".to_string())],
+                    right: vec![("*".to_string(), "$1".to_string())],
+                },
+                vec![(111, "str1".to_string())],
+                vec![(222, "str1".to_string())],
+            ),
         }
     }
 
@@ -177,4 +365,130 @@ mod tests {
         let cpy = Dict::load(&mut cursor).expect("Failed to load dict");
         assert_eq!(org, cpy);
     }
+
+    #[test]
+    fn test_surface_and_entries() {
+        let dict = new_test_dict();
+        assert_eq!(dict.surface(1), Some("key1"));
+        assert_eq!(dict.surface(2), Some("key2"));
+        assert_eq!(dict.surface(4), None);
+
+        let entries = dict.entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].id, 2);
+        assert_eq!(entries[1].surface, "key2");
+        assert_eq!(entries[1].morph, &morph::Morph::new(444, 555, 666));
+        assert_eq!(
+            entries[1].features,
+            vec!["str1", "str2", "str3", "str6", "str7", "str8"]
+        );
+    }
+
+    fn new_lookup_test_dict() -> Dict {
+        let keywords = ["あ".to_string(), "あい".to_string(), "い".to_string()];
+        let mut char_category = vec![0; 0x3100];
+        for ch in 'ぁ'..='ゟ' {
+            char_category[ch as usize] = 1;
+        }
+        char_category['a' as usize] = 2;
+        Dict {
+            morphs: morph::Morphs::from(vec![
+                morph::Morph::new(1, 1, 100),
+                morph::Morph::new(2, 2, 200),
+                morph::Morph::new(3, 3, 300),
+            ]),
+            morph_feature_table: morph_feature::MorphFeatureTableBuilder::from(vec![
+                vec!["感動詞"],
+                vec!["名詞"],
+                vec!["名詞"],
+            ])
+            .build(),
+            connection_table: connection::ConnectionTable::from(matrix_def::MatrixDef {
+                row: 1,
+                col: 1,
+                data: vec![0],
+            }),
+            index_table: index::IndexTable::build(&keywords).expect("Failed to build index table"),
+            char_category_def: char_category_def::CharCategoryDef {
+                char_class: vec![
+                    "DEFAULT".to_string(),
+                    "HIRAGANA".to_string(),
+                    "ALPHA".to_string(),
+                ],
+                char_category,
+                invoke_list: vec![false, false, true],
+                group_list: vec![false, true, true],
+            },
+            unk_dict: unk_dict::UnkDict {
+                morphs: morph::Morphs::from(vec![
+                    morph::Morph::new(4, 4, 1000),
+                    morph::Morph::new(5, 5, 2000),
+                    morph::Morph::new(6, 6, 3000),
+                ]),
+                morph_feature_table: morph_feature::MorphFeatureTableBuilder::from(vec![
+                    vec!["記号"],
+                    vec!["名詞"],
+                    vec!["英字"],
+                ])
+                .build(),
+                char_category_to_morph_id: vec![(0, (1, 1)), (1, (2, 1)), (2, (3, 1))]
+                    .into_iter()
+                    .collect(),
+            },
+            surface_table: surface::SurfaceTable::build(&keywords),
+            context_id_def: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let dict = new_lookup_test_dict();
+        let results = dict
+            .lookup("あいうaa")
+            .into_iter()
+            .map(|l| {
+                (
+                    l.class,
+                    l.char_pos,
+                    l.byte_pos,
+                    l.entry.surface,
+                    l.entry.morph.cost,
+                    l.entry.features,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                (EntryClass::Known, 0, 0, "あ", 100, vec!["感動詞"]),
+                (EntryClass::Known, 0, 0, "あい", 200, vec!["名詞"]),
+                (EntryClass::Known, 1, 3, "い", 300, vec!["名詞"]),
+                // no known words, so the hiragana are grouped into an unknown word
+                (EntryClass::Unknown, 2, 6, "う", 2000, vec!["名詞"]),
+                // ALPHA always invokes unknown word processing
+                (EntryClass::Unknown, 3, 9, "aa", 3000, vec!["英字"]),
+                (EntryClass::Unknown, 4, 10, "a", 3000, vec!["英字"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_unknown_words() {
+        let dict = new_lookup_test_dict();
+        assert_eq!(dict.search_unknown_words("ううaa", false), Some((2..3, 6)));
+        assert_eq!(dict.search_unknown_words("ううaa", true), None);
+        assert_eq!(dict.search_unknown_words("aaう", true), Some((3..4, 2)));
+        assert_eq!(dict.search_unknown_words("", false), None);
+    }
+
+    #[test]
+    fn test_load_without_surfaces() {
+        let mut org = new_test_dict();
+        org.surface_table = surface::SurfaceTable::default();
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        org.build(&mut cursor).expect("Failed to build dict");
+        let cpy = Dict::load(&mut cursor).expect("Failed to load dict");
+        assert_eq!(cpy.surface(1), None);
+        assert_eq!(cpy.entries().count(), 0);
+    }
 }
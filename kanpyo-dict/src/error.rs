@@ -48,6 +48,18 @@ pub enum KanpyoError {
     #[error("Failed to build dictionary: {0}")]
     DictBuild(String),
 
+    /// Surface strings are required but the dictionary has none
+    #[error("Dictionary has no surface strings")]
+    MissingSurfaces,
+
+    /// No entry shares features with a new entry
+    #[error("Failed to estimate context ids and cost: {0}")]
+    Estimate(String),
+
+    /// Context ids of features are not defined in left-id.def or right-id.def
+    #[error("Context ids not found for features: {0}")]
+    ContextIdNotFound(String),
+
     /// Trie build error
     #[error("Failed to build trie: {0}")]
     TrieBuild(String),
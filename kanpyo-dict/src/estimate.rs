@@ -0,0 +1,348 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use encoding_rs::Encoding;
+
+use crate::{
+    context_id::ContextIdDef,
+    dict::Dict,
+    error::{KanpyoError, Result},
+    export,
+    morph_feature::MorphFeatureID,
+};
+
+/// Estimate is the estimated context ids and word cost of a new entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub left_id: i16,
+    pub right_id: i16,
+    pub cost: i16,
+    /// Number of leading features shared with the entries the estimate comes from.
+    pub matched: usize,
+}
+
+// Contexts maps context ids to the sorted costs of entries.
+type Contexts = BTreeMap<(i16, i16), Vec<i16>>;
+
+/// Estimator estimates context ids and word costs of new entries from their features
+/// like `mecab-dict-index -a`.
+///
+/// Entries of the dictionary are grouped by the leading features, e.g. POS and conjugation.
+/// A new entry takes the most common context ids among the entries sharing the longest prefix
+/// of features with it, and the median cost of those entries. If the dictionary has context id
+/// definitions (rewrite.def, left-id.def and right-id.def), the context ids are resolved by them
+/// instead. Estimating from a dictionary given by `train::Trainer::trained_dict` uses the trained costs.
+#[derive(Debug, Clone)]
+pub struct Estimator {
+    depth: usize,
+    names: HashMap<String, MorphFeatureID>,
+    // prefix of feature ids -> contexts of the entries sharing it
+    groups: HashMap<Vec<MorphFeatureID>, Contexts>,
+    context_id_def: ContextIdDef,
+}
+
+impl Estimator {
+    /// Builds an estimator that compares at most `depth` leading features, e.g. 6 for IPADIC.
+    pub fn new(dict: &Dict, depth: usize) -> Self {
+        let table = &dict.morph_feature_table;
+        let names = table
+            .name_list
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.clone(), id as MorphFeatureID))
+            .collect();
+        let mut groups: HashMap<_, Contexts> = HashMap::new();
+        for (morph, ids) in dict.morphs.iter().zip(&table.morph_features) {
+            for k in 1..=depth.min(ids.len()) {
+                groups
+                    .entry(ids[..k].to_vec())
+                    .or_default()
+                    .entry((morph.left_id, morph.right_id))
+                    .or_default()
+                    .push(morph.cost);
+            }
+        }
+        for costs in groups
+            .values_mut()
+            .flat_map(|contexts| contexts.values_mut())
+        {
+            costs.sort_unstable();
+        }
+        Estimator {
+            depth,
+            names,
+            groups,
+            context_id_def: dict.context_id_def.clone(),
+        }
+    }
+
+    /// Returns `None` if no entry shares even the first feature.
+    pub fn estimate(&self, features: &[&str]) -> Option<Estimate> {
+        let ids = features
+            .iter()
+            .take(self.depth)
+            .map_while(|name| self.names.get(*name).copied())
+            .collect::<Vec<_>>();
+        let estimate = |k: usize, (left_id, right_id): (i16, i16), costs: &[i16]| Estimate {
+            left_id,
+            right_id,
+            cost: costs[costs.len() / 2],
+            matched: k,
+        };
+        let mut groups = (1..=ids.len())
+            .rev()
+            .filter_map(|k| Some((k, self.groups.get(&ids[..k])?)));
+        let resolved = self.context_id_def.context_ids(features);
+        if let Some(context) = resolved {
+            // the cost of entries with the same context ids if any
+            if let Some((k, costs)) = groups
+                .clone()
+                .find_map(|(k, contexts)| Some((k, contexts.get(&context)?)))
+            {
+                return Some(estimate(k, context, costs));
+            }
+        }
+        groups.find_map(|(k, contexts)| {
+            // the first of the most common context ids
+            let (&context, costs) = contexts.iter().rev().max_by_key(|(_, costs)| costs.len())?;
+            Some(estimate(k, resolved.unwrap_or(context), costs))
+        })
+    }
+
+    /// Fills the missing context ids and costs of a lexicon CSV and returns a CSV ready to build.
+    /// Each record is `surface,left_id,right_id,cost,features...`, and empty fields are estimated.
+    pub fn estimate_csv(&self, csv: &str) -> Result<String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv.as_bytes());
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_writer(vec![]);
+        for result in reader.records() {
+            let record = result?;
+            if record.len() < 5 {
+                return Err(KanpyoError::Parse(format!(
+                    "a record needs a surface, context ids, a cost and features: {:?}",
+                    record
+                )));
+            }
+            let mut record = record.iter().map(str::to_string).collect::<Vec<_>>();
+            if record[1..4].iter().any(String::is_empty) {
+                let features = record[4..].iter().map(String::as_str).collect::<Vec<_>>();
+                let estimate = self
+                    .estimate(&features)
+                    .ok_or_else(|| KanpyoError::Estimate(record.join(",")))?;
+                let estimated = [estimate.left_id, estimate.right_id, estimate.cost];
+                for (field, value) in record[1..4].iter_mut().zip(estimated) {
+                    if field.is_empty() {
+                        *field = value.to_string();
+                    }
+                }
+            }
+            writer.write_record(&record)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| KanpyoError::Io(e.into_error()))?;
+        String::from_utf8(bytes).map_err(|_| KanpyoError::EncodingError)
+    }
+
+    /// Reads a lexicon CSV from `input`, fills it by `estimate_csv` and writes it to `output`.
+    pub fn estimate_file(
+        &self,
+        input: &Path,
+        output: &Path,
+        encoding: &'static Encoding,
+    ) -> Result<()> {
+        let byte = fs::read(input)?;
+        let (utf8, _, had_errors) = encoding.decode(&byte);
+        if had_errors {
+            return Err(KanpyoError::EncodingError);
+        }
+        export::write_file(output, &self.estimate_csv(&utf8)?, encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{DictionaryBuilder, config::Config, matrix_def::MatrixDef},
+        char_category_def::CharCategoryDef,
+        connection::ConnectionTable,
+        index::IndexTable,
+        morph::{Morph, Morphs},
+        morph_feature::MorphFeatureTableBuilder,
+        testutil::TempDir,
+        unk_dict::UnkDict,
+    };
+
+    fn new_dict(entries: &[(i16, i16, i16, &[&str])]) -> Dict {
+        Dict::new(
+            Morphs::from(
+                entries
+                    .iter()
+                    .map(|&(left_id, right_id, cost, _)| Morph::new(left_id, right_id, cost))
+                    .collect::<Vec<_>>(),
+            ),
+            MorphFeatureTableBuilder::from(
+                entries
+                    .iter()
+                    .map(|(_, _, _, features)| features.to_vec())
+                    .collect::<Vec<_>>(),
+            )
+            .build(),
+            ConnectionTable::from(MatrixDef {
+                row: 1,
+                col: 1,
+                data: vec![0],
+            }),
+            IndexTable::build(&["a".to_string()]).unwrap(),
+            CharCategoryDef {
+                char_class: vec!["DEFAULT".to_string()],
+                char_category: vec![0],
+                invoke_list: vec![false],
+                group_list: vec![false],
+            },
+            UnkDict {
+                morphs: Default::default(),
+                morph_feature_table: Default::default(),
+                char_category_to_morph_id: Default::default(),
+            },
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn test_estimate() {
+        let dict = new_dict(&[
+            (1, 1, 3000, &["名詞", "一般", "*", "*"]),
+            (1, 1, 5000, &["名詞", "一般", "*", "*"]),
+            (1, 1, 4000, &["名詞", "一般", "*", "*"]),
+            (2, 2, 100, &["名詞", "一般", "*", "*"]),
+            (3, 3, 6000, &["名詞", "固有名詞", "地域", "一般"]),
+            (4, 4, 2000, &["助詞", "係助詞", "*", "*"]),
+        ]);
+        let estimator = Estimator::new(&dict, 4);
+        assert_eq!(
+            estimator.estimate(&["名詞", "一般", "*", "*", "新語"]),
+            Some(Estimate {
+                left_id: 1,
+                right_id: 1,
+                cost: 4000,
+                matched: 4,
+            })
+        );
+        // falls back to the longest prefix
+        assert_eq!(
+            estimator.estimate(&["名詞", "固有名詞", "人名", "姓"]),
+            Some(Estimate {
+                left_id: 3,
+                right_id: 3,
+                cost: 6000,
+                matched: 2,
+            })
+        );
+        assert_eq!(estimator.estimate(&["動詞", "自立"]), None);
+        assert_eq!(estimator.estimate(&[]), None);
+
+        // context ids resolved by the definitions take precedence
+        let mut dict = dict;
+        dict.context_id_def = ContextIdDef::new(
+            crate::builder::rewrite_def::RewriteDef {
+                unigram: vec![],
+                left: vec![("*,*".to_string(), "$1,$2".to_string())],
+                right: vec![("*,*".to_string(), "$1,$2".to_string())],
+            },
+            vec![
+                (2, "名詞,一般".to_string()),
+                (5, "名詞,固有名詞".to_string()),
+            ],
+            vec![
+                (2, "名詞,一般".to_string()),
+                (5, "名詞,固有名詞".to_string()),
+            ],
+        );
+        let estimator = Estimator::new(&dict, 4);
+        assert_eq!(
+            estimator.estimate(&["名詞", "一般", "*", "*"]),
+            Some(Estimate {
+                left_id: 2,
+                right_id: 2,
+                cost: 100,
+                matched: 4,
+            })
+        );
+        assert_eq!(
+            estimator.estimate(&["名詞", "固有名詞", "人名", "姓"]),
+            Some(Estimate {
+                left_id: 5,
+                right_id: 5,
+                cost: 6000,
+                matched: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_estimate_csv() {
+        let dict = new_dict(&[
+            (1, 1, 3000, &["名詞", "一般", "*", "*"]),
+            (4, 4, 2000, &["助詞", "係助詞", "*", "*"]),
+        ]);
+        let estimator = Estimator::new(&dict, 4);
+        assert_eq!(
+            estimator
+                .estimate_csv(
+                    "かんぴょう,,,,名詞,一般,*,*\nは,,,100,助詞,係助詞,*,*\nも,4,4,10,助詞\n"
+                )
+                .unwrap(),
+            "かんぴょう,1,1,3000,名詞,一般,*,*\nは,4,4,100,助詞,係助詞,*,*\nも,4,4,10,助詞\n"
+        );
+        assert!(matches!(
+            estimator.estimate_csv("走る,,,,動詞,自立\n"),
+            Err(KanpyoError::Estimate(_))
+        ));
+        assert!(matches!(
+            estimator.estimate_csv("かんぴょう,,,\n"),
+            Err(KanpyoError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_file() {
+        let dir = TempDir::new("kanpyo-estimate-test");
+        let dir = dir.path();
+        let write = |name: &str, text: &str| {
+            fs::write(dir.join(name), encoding_rs::EUC_JP.encode(text).0).unwrap();
+        };
+        write("char.def", "DEFAULT 0 1 0\n");
+        write("matrix.def", "2 2\n0 0 0\n0 1 0\n1 0 0\n1 1 0\n");
+        write("unk.def", "DEFAULT,1,1,3000,記号,一般\n");
+        write("seed.csv", "東京,1,1,3000,名詞,固有名詞\n");
+        let config = Config::new(dir, encoding_rs::EUC_JP);
+        let dict = DictionaryBuilder::from_config(&config).unwrap();
+
+        write("user.txt", "京都,,,,名詞,固有名詞\n");
+        Estimator::new(&dict, 2)
+            .estimate_file(
+                &dir.join("user.txt"),
+                &dir.join("user.csv"),
+                encoding_rs::EUC_JP,
+            )
+            .unwrap();
+        let rebuilt = DictionaryBuilder::from_config(&config).unwrap();
+        assert_eq!(rebuilt.morphs.len(), 2);
+        assert!(
+            rebuilt
+                .morphs
+                .iter()
+                .all(|morph| *morph == Morph::new(1, 1, 3000))
+        );
+    }
+}
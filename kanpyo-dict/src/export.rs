@@ -0,0 +1,344 @@
+use std::{collections::BTreeMap, fmt::Write as _, fs};
+
+use encoding_rs::Encoding;
+
+use crate::{
+    builder::config::Config,
+    context_id::ContextIdDef,
+    dict::Dict,
+    error::{KanpyoError, Result},
+    trie::da::KeywordID,
+};
+
+// export writes the MeCab source files of a dictionary into `config.root_path`.
+// The files are encoded in `config.encoding` and named after the file names of `config`,
+// so `DictionaryBuilder::from_config(config)` rebuilds the same dictionary.
+pub fn export(dict: &Dict, config: &Config) -> Result<()> {
+    fs::create_dir_all(config.root_path)?;
+    for (file_name, csv) in csv_files(dict)? {
+        write_file(&config.root_path.join(file_name), &csv, config.encoding)?;
+    }
+    write_file(
+        &config.root_path.join(config.matrix_def_file_name),
+        &matrix_def(dict),
+        config.encoding,
+    )?;
+    write_file(
+        &config.root_path.join(config.char_def_file_name),
+        &char_def(dict),
+        config.encoding,
+    )?;
+    write_file(
+        &config.root_path.join(config.unk_def_file_name),
+        &unk_def(dict)?,
+        config.encoding,
+    )?;
+    // context id definitions are optional
+    let def = &dict.context_id_def;
+    for (file_name, ids) in [
+        (config.left_id_def_file_name, &def.left_ids),
+        (config.right_id_def_file_name, &def.right_ids),
+    ] {
+        if !ids.is_empty() {
+            write_file(
+                &config.root_path.join(file_name),
+                &id_def(ids),
+                config.encoding,
+            )?;
+        }
+    }
+    if !(def.unigram.is_empty() && def.left.is_empty() && def.right.is_empty()) {
+        write_file(
+            &config.root_path.join(config.rewrite_def_file_name),
+            &rewrite_def(def),
+            config.encoding,
+        )?;
+    }
+    Ok(())
+}
+
+pub(crate) fn write_file(
+    path: &std::path::Path,
+    text: &str,
+    encoding: &'static Encoding,
+) -> Result<()> {
+    let (bytes, _, had_errors) = encoding.encode(text);
+    if had_errors {
+        return Err(KanpyoError::EncodingError);
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+fn write_record<'a>(
+    writer: &mut csv::Writer<Vec<u8>>,
+    head: &str,
+    morph: &crate::morph::Morph,
+    features: impl IntoIterator<Item = &'a str>,
+) -> Result<()> {
+    let mut record = vec![
+        head.to_string(),
+        morph.left_id.to_string(),
+        morph.right_id.to_string(),
+        morph.cost.to_string(),
+    ];
+    record.extend(features.into_iter().map(str::to_string));
+    writer.write_record(&record)?;
+    Ok(())
+}
+
+fn into_string(writer: csv::Writer<Vec<u8>>) -> Result<String> {
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| KanpyoError::Io(e.into_error()))?;
+    String::from_utf8(bytes).map_err(|_| KanpyoError::EncodingError)
+}
+
+// csv_files returns lexicon CSV files and their names.
+// A CSV file requires the same number of columns in every record, so entries are split by the number of features.
+pub fn csv_files(dict: &Dict) -> Result<Vec<(String, String)>> {
+    if dict.surface_table.len() != dict.morphs.len() {
+        return Err(KanpyoError::MissingSurfaces);
+    }
+    let mut writers = BTreeMap::new();
+    for entry in dict.entries() {
+        let writer = writers.entry(entry.features.len()).or_insert_with(|| {
+            csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(vec![])
+        });
+        write_record(writer, entry.surface, entry.morph, entry.features)?;
+    }
+    let single = writers.len() == 1;
+    writers
+        .into_iter()
+        .map(|(len, writer)| {
+            let file_name = if single {
+                "dict.csv".to_string()
+            } else {
+                format!("dict.{}.csv", len)
+            };
+            Ok((file_name, into_string(writer)?))
+        })
+        .collect()
+}
+
+// matrix_def returns the connection table in the matrix.def format.
+pub fn matrix_def(dict: &Dict) -> String {
+    let table = &dict.connection_table;
+    let mut text = format!("{} {}\n", table.row(), table.col());
+    for row in 0..table.row() {
+        for col in 0..table.col() {
+            writeln!(text, "{} {} {}", row, col, table.get(row, col)).expect("write to String");
+        }
+    }
+    text
+}
+
+// char_def returns the character category definition in the char.def format.
+// Compatibility categories and the length of unknown words are not stored in the dictionary,
+// so only the first category of each character is written and the length is always 0.
+pub fn char_def(dict: &Dict) -> String {
+    let def = &dict.char_category_def;
+    let mut text = String::new();
+    for (i, class) in def.char_class.iter().enumerate() {
+        writeln!(
+            text,
+            "{} {} {} 0",
+            class, def.invoke_list[i] as u8, def.group_list[i] as u8
+        )
+        .expect("write to String");
+    }
+    let mut start = 0;
+    while start < def.char_category.len() {
+        let category = def.char_category[start];
+        let mut end = start;
+        while end + 1 < def.char_category.len() && def.char_category[end + 1] == category {
+            end += 1;
+        }
+        // unlisted characters fall into the first category
+        if category != 0 {
+            let class = &def.char_class[category as usize];
+            if start == end {
+                writeln!(text, "0x{:04X} {}", start, class).expect("write to String");
+            } else {
+                writeln!(text, "0x{:04X}..0x{:04X} {}", start, end, class)
+                    .expect("write to String");
+            }
+        }
+        start = end + 1;
+    }
+    text
+}
+
+// id_def returns context ids in the left-id.def or right-id.def format.
+pub fn id_def(ids: &BTreeMap<String, i16>) -> String {
+    let mut ids = ids.iter().map(|(f, &id)| (id, f)).collect::<Vec<_>>();
+    ids.sort();
+    let mut text = String::new();
+    for (id, features) in ids {
+        writeln!(text, "{} {}", id, features).expect("write to String");
+    }
+    text
+}
+
+// rewrite_def returns the rewrite rules in the rewrite.def format.
+pub fn rewrite_def(def: &ContextIdDef) -> String {
+    let mut text = String::new();
+    for (section, rewriter) in [
+        ("unigram", &def.unigram),
+        ("left", &def.left),
+        ("right", &def.right),
+    ] {
+        writeln!(text, "[{} rewrite]", section).expect("write to String");
+        for rule in &rewriter.0 {
+            writeln!(text, "{} {}", rule.pattern.join(","), rule.result).expect("write to String");
+        }
+    }
+    text
+}
+
+// unk_def returns the unknown word dictionary in the unk.def format.
+pub fn unk_def(dict: &Dict) -> Result<String> {
+    let unk_dict = &dict.unk_dict;
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(vec![]);
+    for (&char_category, &(morph_id, count)) in &unk_dict.char_category_to_morph_id {
+        let class = dict
+            .char_category_def
+            .char_class
+            .get(char_category as usize)
+            .ok_or_else(|| KanpyoError::CharCategoryNotFound(char_category.to_string()))?;
+        for id in morph_id..morph_id + count as KeywordID {
+            write_record(
+                &mut writer,
+                class,
+                &unk_dict.morphs[id - 1],
+                unk_dict
+                    .morph_feature_table
+                    .features(id)
+                    .unwrap_or_default(),
+            )?;
+        }
+    }
+    into_string(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::DictionaryBuilder, testutil::TempDir};
+
+    const CHAR_DEF: &str = "\
+DEFAULT 0 1 0
+SPACE 0 1 0
+KANJI 0 0 2
+HIRAGANA 1 1 0
+0x0020 SPACE
+0x3041..0x309F HIRAGANA
+0x4E00..0x9FA5 KANJI
+";
+    const MATRIX_DEF: &str = "2 2\n0 0 0\n0 1 10\n1 0 -20\n1 1 30\n";
+    const UNK_DEF: &str = "\
+DEFAULT,1,1,3000,記号,一般,*,*,*,*,*
+SPACE,0,0,1000,記号,空白,*,*,*,*,*
+KANJI,1,1,5000,名詞,一般,*,*,*,*,*
+KANJI,1,1,6000,名詞,固有名詞,*,*,*,*,*
+HIRAGANA,1,1,4000,名詞,一般,*,*,*,*,*
+";
+    const NOUN_CSV: &str = "\
+東京,1,1,3000,名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー
+すもも,1,1,7000,名詞,一般,*,*,*,*,すもも,スモモ,スモモ
+\"a,b\",1,1,5000,名詞,一般,*,*,*,*,\"a,b\",*,*
+もも,1,1,7000,名詞,一般,*,*,*,*,もも,モモ,モモ
+";
+    const PARTICLE_CSV: &str = "\
+も,0,0,4000,助詞,係助詞,*,*,*,*,も,モ,モ
+もも,0,1,8000,助詞,係助詞,*,*,*,*,もも,モモ,モモ
+";
+    // a lexicon with fewer features than the others
+    const SHORT_CSV: &str = "の,0,0,4000,助詞,連体化\n";
+    // context ids are resolved by rewrite.def, left-id.def and right-id.def
+    const USER_CSV: &str = "ももも,,,2000,名詞,一般,*,*,*,*,ももも,モモモ,モモモ\n";
+    const REWRITE_DEF: &str = "\
+[unigram rewrite]
+*,*,*,*,*,*,* $1,$2,$3,$4,$5,$6,$7
+[left rewrite]
+助詞,(係助詞|連体化) $1,$2
+*,* $1,*
+[right rewrite]
+* $1
+";
+    const LEFT_ID_DEF: &str = "0 助詞,係助詞\n1 名詞,*\n";
+    const RIGHT_ID_DEF: &str = "0 助詞\n1 名詞\n";
+
+    fn build_from(dir: &std::path::Path, encoding: &'static Encoding) -> Dict {
+        DictionaryBuilder::from_config(&Config::new(dir, encoding)).expect("Failed to build dict")
+    }
+
+    #[test]
+    fn test_export_and_rebuild() {
+        let src = TempDir::new("kanpyo-export-test-src");
+        let src = src.path();
+        fs::write(src.join("char.def"), CHAR_DEF).unwrap();
+        fs::write(src.join("matrix.def"), MATRIX_DEF).unwrap();
+        fs::write(src.join("unk.def"), UNK_DEF).unwrap();
+        fs::write(src.join("noun.csv"), NOUN_CSV).unwrap();
+        fs::write(src.join("particle.csv"), PARTICLE_CSV).unwrap();
+        fs::write(src.join("short.csv"), SHORT_CSV).unwrap();
+        fs::write(src.join("user.csv"), USER_CSV).unwrap();
+        fs::write(src.join("rewrite.def"), REWRITE_DEF).unwrap();
+        fs::write(src.join("left-id.def"), LEFT_ID_DEF).unwrap();
+        fs::write(src.join("right-id.def"), RIGHT_ID_DEF).unwrap();
+
+        let org = build_from(src, encoding_rs::UTF_8);
+        let user = org
+            .entries()
+            .find(|entry| entry.surface == "ももも")
+            .unwrap();
+        assert_eq!(*user.morph, crate::morph::Morph::new(1, 1, 2000));
+        for encoding in [encoding_rs::UTF_8, encoding_rs::EUC_JP] {
+            let out = TempDir::new("kanpyo-export-test-out");
+            export(&org, &Config::new(out.path(), encoding)).expect("Failed to export dict");
+            let cpy = build_from(out.path(), encoding);
+            assert_eq!(org, cpy);
+        }
+    }
+
+    #[test]
+    fn test_export_without_surfaces() {
+        let mut dict = Dict::new(
+            crate::morph::Morphs::from(vec![crate::morph::Morph::new(0, 0, 0)]),
+            Default::default(),
+            crate::connection::ConnectionTable::from(crate::builder::matrix_def::MatrixDef {
+                row: 1,
+                col: 1,
+                data: vec![0],
+            }),
+            crate::index::IndexTable::build(&["a".to_string()]).unwrap(),
+            crate::char_category_def::CharCategoryDef {
+                char_class: vec!["DEFAULT".to_string()],
+                char_category: vec![0],
+                invoke_list: vec![false],
+                group_list: vec![false],
+            },
+            crate::unk_dict::UnkDict {
+                morphs: Default::default(),
+                morph_feature_table: Default::default(),
+                char_category_to_morph_id: Default::default(),
+            },
+            Default::default(),
+        );
+        assert!(matches!(
+            csv_files(&dict),
+            Err(KanpyoError::MissingSurfaces)
+        ));
+        dict.surface_table = crate::surface::SurfaceTable::build(&["a".to_string()]);
+        assert_eq!(
+            csv_files(&dict).unwrap(),
+            vec![("dict.csv".to_string(), "a,0,0,0\n".to_string())]
+        );
+    }
+}
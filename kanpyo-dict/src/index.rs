@@ -51,6 +51,26 @@ impl IndexTable {
         }
         Some(results)
     }
+
+    pub fn search_predictive_of(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Option<Vec<(trie::da::KeywordID, String)>> {
+        // duplicated keywords share a single entry in the double array
+        let ids_and_keywords = self.da.search_predictive_of(prefix, limit)?;
+        let mut results = vec![];
+        for (id, keyword) in ids_and_keywords {
+            let dup = *self.dup.get(&id).unwrap_or(&0);
+            for i in 0..=dup {
+                if results.len() >= limit {
+                    return Some(results);
+                }
+                results.push((id + i as isize, keyword.clone()));
+            }
+        }
+        Some(results)
+    }
 }
 
 impl DictReadWrite for IndexTable {
@@ -149,6 +169,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_index_table_search_predictive() {
+        let keywords = vec![
+            "apple".to_string(),
+            "apple".to_string(),
+            "applepie".to_string(),
+            "banana".to_string(),
+        ];
+        let index = IndexTable::build(&keywords).expect("Failed to build index");
+
+        let results = index.search_predictive_of("app", usize::MAX);
+        assert_eq!(
+            results,
+            Some(vec![
+                (1, "apple".to_string()),
+                (2, "apple".to_string()),
+                (3, "applepie".to_string()),
+            ])
+        );
+
+        // the limit counts duplicated keywords individually
+        let results = index.search_predictive_of("app", 2);
+        assert_eq!(
+            results,
+            Some(vec![(1, "apple".to_string()), (2, "apple".to_string())])
+        );
+
+        let results = index.search_predictive_of("cherry", usize::MAX);
+        assert!(results.is_none(), "Should not find 'cherry'");
+    }
+
     #[test]
     fn test_index_table_write_read() {
         let keywords = vec![
@@ -1,10 +1,17 @@
 pub mod builder;
 pub mod char_category_def;
 pub mod connection;
+pub mod context_id;
 pub mod dict;
 pub mod error;
+pub mod estimate;
+pub mod export;
 pub mod index;
 pub mod morph;
 pub mod morph_feature;
+pub mod surface;
+#[doc(hidden)]
+pub mod testutil;
+pub mod train;
 pub mod trie;
 pub mod unk_dict;
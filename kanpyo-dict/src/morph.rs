@@ -41,6 +41,18 @@ impl Morphs {
             cost,
         });
     }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Morph> {
+        self.0.iter()
+    }
 }
 
 impl Index<KeywordID> for Morphs {
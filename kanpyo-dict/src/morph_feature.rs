@@ -1,6 +1,6 @@
 use bincode::{Decode, Encode};
 
-use crate::dict::DictReadWrite;
+use crate::{dict::DictReadWrite, trie::da::KeywordID};
 
 // MorphFeatureTable represents a table for managing part of speeches.
 #[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
@@ -17,6 +17,20 @@ pub type MorphFeatureIDs = Vec<MorphFeatureID>;
 
 const MAX_FEATURE_ID: MorphFeatureID = MorphFeatureID::MAX;
 
+impl MorphFeatureTable {
+    // features returns the feature names of a morph id which starts from 1.
+    pub fn features(&self, id: KeywordID) -> Option<Vec<&str>> {
+        let index = usize::try_from(id).ok()?.checked_sub(1)?;
+        Some(
+            self.morph_features
+                .get(index)?
+                .iter()
+                .map(|&idx| self.name_list[idx as usize].as_str())
+                .collect(),
+        )
+    }
+}
+
 impl DictReadWrite for MorphFeatureTable {
     fn write_dict<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
         match bincode::encode_to_vec(self, bincode::config::standard()) {
@@ -151,6 +165,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_features() {
+        let table = MorphFeatureTableBuilder::from(vec![
+            vec!["動詞", "自立", "*"],
+            vec!["名詞", "一般", "*"],
+        ])
+        .build();
+        assert_eq!(table.features(1), Some(vec!["動詞", "自立", "*"]));
+        assert_eq!(table.features(2), Some(vec!["名詞", "一般", "*"]));
+        assert_eq!(table.features(0), None);
+        assert_eq!(table.features(3), None);
+    }
+
     #[test]
     fn test_read_and_write() {
         let data = vec![
@@ -0,0 +1,142 @@
+use std::io::Read;
+
+use crate::{dict::DictReadWrite, trie::da::KeywordID};
+
+// SurfaceTable represents a pool of surface strings indexed by morph id.
+// Duplicated surfaces share the same span of the pool.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceTable {
+    pool: String,
+    // (start, length) in bytes of the pool
+    spans: Vec<(u32, u32)>,
+}
+
+impl SurfaceTable {
+    // build returns a SurfaceTable whose n-th surface is the n-th keyword.
+    pub fn build(sorted_keywords: &[String]) -> Self {
+        let mut pool = String::new();
+        let mut spans: Vec<(u32, u32)> = Vec::with_capacity(sorted_keywords.len());
+        let mut prev: Option<&String> = None;
+        for keyword in sorted_keywords {
+            if let (Some(prev), Some(&span)) = (prev, spans.last())
+                && prev == keyword
+            {
+                spans.push(span);
+                continue;
+            }
+            spans.push((pool.len() as u32, keyword.len() as u32));
+            pool.push_str(keyword);
+            prev = Some(keyword);
+        }
+        Self { pool, spans }
+    }
+
+    // get returns the surface of a morph id which starts from 1.
+    pub fn get(&self, id: KeywordID) -> Option<&str> {
+        let index = usize::try_from(id).ok()?.checked_sub(1)?;
+        let &(start, len) = self.spans.get(index)?;
+        self.pool.get(start as usize..(start + len) as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+impl DictReadWrite for SurfaceTable {
+    fn write_dict<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(&(self.pool.len() as u64).to_le_bytes())?;
+        w.write_all(self.pool.as_bytes())?;
+        w.write_all(&(self.spans.len() as u64).to_le_bytes())?;
+        for &(start, len) in &self.spans {
+            w.write_all(&start.to_le_bytes())?;
+            w.write_all(&len.to_le_bytes())?;
+        }
+        Ok(())
+    }
+    fn from_dict<R: std::io::Read>(r: &mut R) -> std::io::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut buf = [0; 8];
+        r.read_exact(&mut buf)?;
+        let pool = read_bytes(r, u64::from_le_bytes(buf))?;
+        let pool = String::from_utf8(pool)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        r.read_exact(&mut buf)?;
+        let len = u64::from_le_bytes(buf).checked_mul(8).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "too many surfaces")
+        })?;
+        let spans = read_bytes(r, len)?
+            .chunks_exact(8)
+            .map(|span| {
+                let (start, len) = span.split_at(4);
+                (
+                    u32::from_le_bytes(start.try_into().expect("4 bytes")),
+                    u32::from_le_bytes(len.try_into().expect("4 bytes")),
+                )
+            })
+            .collect();
+        Ok(Self { pool, spans })
+    }
+}
+
+// read_bytes reads `len` bytes, allocating as many as are actually read
+// so that a corrupt length fails with an error instead of exhausting memory.
+fn read_bytes<R: Read>(r: &mut R, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    r.by_ref().take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_get() {
+        let keywords = vec![
+            "apple".to_string(),
+            "apple".to_string(),
+            "バナナ".to_string(),
+            "cherry".to_string(),
+        ];
+        let table = SurfaceTable::build(&keywords);
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.pool, "appleバナナcherry");
+        for (i, keyword) in keywords.iter().enumerate() {
+            assert_eq!(table.get(i as KeywordID + 1), Some(keyword.as_str()));
+        }
+        assert_eq!(table.get(0), None);
+        assert_eq!(table.get(5), None);
+    }
+
+    #[test]
+    fn test_read_and_write() {
+        let keywords = vec!["東京".to_string(), "東京".to_string(), "大学".to_string()];
+        let org = SurfaceTable::build(&keywords);
+        let mut buf = Vec::new();
+        org.write_dict(&mut buf)
+            .expect("Failed to write SurfaceTable");
+        let cpy =
+            SurfaceTable::from_dict(&mut buf.as_slice()).expect("Failed to read SurfaceTable");
+        assert_eq!(org, cpy);
+
+        // a corrupt length fails without allocating it
+        let mut corrupt = u64::MAX.to_le_bytes().to_vec();
+        corrupt.extend_from_slice(&buf[8..]);
+        assert!(SurfaceTable::from_dict(&mut corrupt.as_slice()).is_err());
+        let pool_len = org.pool.len() + 8;
+        let mut corrupt = buf[..pool_len].to_vec();
+        corrupt.extend_from_slice(&(u64::MAX / 4).to_le_bytes());
+        assert!(SurfaceTable::from_dict(&mut corrupt.as_slice()).is_err());
+    }
+}
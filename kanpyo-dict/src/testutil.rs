@@ -0,0 +1,51 @@
+//! Test support shared by the crates of the workspace, which isn't a part of the API.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    builder::{DictionaryBuilder, config::Config},
+    dict::Dict,
+};
+
+/// Returns the directory of the sources of the test dictionary in UTF-8,
+/// which the Python tests also build.
+pub fn test_dict_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/dict")
+}
+
+/// Builds the test dictionary from `test_dict_dir`.
+pub fn test_dict() -> Dict {
+    DictionaryBuilder::from_config(&Config::new(&test_dict_dir(), encoding_rs::UTF_8))
+        .expect("Failed to build the test dictionary")
+}
+
+/// TempDir is a directory unique to a test, which is removed when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "{name}-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Failed to create a temporary directory");
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
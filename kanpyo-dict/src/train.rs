@@ -0,0 +1,503 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use encoding_rs::Encoding;
+
+use crate::{
+    builder::matrix_def::MatrixDef,
+    connection::ConnectionTable,
+    dict::{Dict, EntryClass},
+    error::{KanpyoError, Result},
+    morph::{Morph, Morphs},
+    trie::da::KeywordID,
+};
+
+/// Morpheme is an annotated word in a training corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Morpheme {
+    pub surface: String,
+    pub features: Vec<String>,
+}
+
+/// Corpus is a list of annotated sentences in the MeCab output format.
+/// Each line is a surface and comma-separated features separated by a tab, and `EOS` ends a sentence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Corpus {
+    pub sentences: Vec<Vec<Morpheme>>,
+}
+
+impl Corpus {
+    pub fn from_file(path: &Path, encoding: &'static Encoding) -> Result<Self> {
+        let byte = fs::read(path)?;
+        let (utf8, _, had_errors) = encoding.decode(&byte);
+        if had_errors {
+            return Err(KanpyoError::EncodingError);
+        }
+        Self::parse(&utf8)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut sentences = vec![];
+        let mut sentence = vec![];
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            if line == "EOS" {
+                if !sentence.is_empty() {
+                    sentences.push(std::mem::take(&mut sentence));
+                }
+                continue;
+            }
+            let (surface, features) = line.split_once('\t').ok_or_else(|| {
+                KanpyoError::Parse(format!("missing a tab at line {}: {}", i + 1, line))
+            })?;
+            sentence.push(Morpheme {
+                surface: surface.to_string(),
+                features: features.split(',').map(str::to_string).collect(),
+            });
+        }
+        if !sentence.is_empty() {
+            return Err(KanpyoError::Parse(
+                "the last sentence is not terminated by EOS".to_string(),
+            ));
+        }
+        Ok(Corpus { sentences })
+    }
+}
+
+/// TrainConfig holds the hyperparameters of `Trainer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainConfig {
+    /// Number of passes over the corpus.
+    pub epochs: usize,
+    /// Step size of AdaGrad.
+    pub learning_rate: f64,
+    /// Inverse strength of the L2 regularization like `-c` of `mecab-cost-train`.
+    /// Larger values fit the corpus more closely.
+    pub regularization: f64,
+    /// Scale between a weight and a cost like `cost-factor` of MeCab.
+    pub cost_factor: f64,
+    /// Number of leading features used as POS features, e.g. 4 for IPADIC.
+    pub pos_depth: usize,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            epochs: 50,
+            learning_rate: 0.1,
+            regularization: 1.0,
+            cost_factor: 700.0,
+            pos_depth: 4,
+        }
+    }
+}
+
+// Node is a candidate word of a training sentence.
+#[derive(Debug, Clone)]
+struct Node {
+    left_id: usize,
+    right_id: usize,
+    // score of the seed dictionary cost
+    base: f64,
+    features: Vec<usize>,
+    // indices of the nodes that can precede this node
+    previous: Vec<usize>,
+}
+
+// Sentence is the lattice of a training sentence.
+// The first node is BOS, the last one is EOS and nodes are sorted by their start positions.
+#[derive(Debug, Clone)]
+struct Sentence {
+    nodes: Vec<Node>,
+    // indices of the annotated path from the first word to EOS
+    gold: Vec<usize>,
+}
+
+fn log_add_exp(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    if b == f64::NEG_INFINITY {
+        return a;
+    }
+    a.max(b) + (-(a - b).abs()).exp().ln_1p()
+}
+
+// unigram_keys returns the feature names of a word.
+// POS prefixes are shared by all words and the word itself lets each entry get its own cost.
+fn unigram_keys(class: EntryClass, id: KeywordID, features: &[&str], depth: usize) -> Vec<String> {
+    let mut keys = (1..=depth.min(features.len()))
+        .map(|k| format!("P{}:{}", k, features[..k].join(",")))
+        .collect::<Vec<_>>();
+    keys.push(match class {
+        EntryClass::Known => format!("K:{}", id),
+        EntryClass::Unknown => format!("U:{}", id),
+    });
+    keys
+}
+
+/// Trainer learns dictionary costs from an annotated corpus with a CRF like `mecab-cost-train`.
+///
+/// Word costs come from POS prefix and word features, and connection costs come from
+/// pairs of context ids. Weights are learned as corrections to the costs of the seed
+/// dictionary, so entries and connections that never appear in the corpus keep their costs.
+#[derive(Debug, Clone)]
+pub struct Trainer<'a> {
+    dict: &'a Dict,
+    config: TrainConfig,
+    // unigram feature ids. Bigram features take the first `row * col` weights.
+    features: HashMap<String, usize>,
+    weights: Vec<f64>,
+    sentences: Vec<Sentence>,
+    skipped: usize,
+}
+
+impl<'a> Trainer<'a> {
+    pub fn new(dict: &'a Dict, corpus: &Corpus, config: TrainConfig) -> Self {
+        let table = &dict.connection_table;
+        let mut trainer = Trainer {
+            dict,
+            config,
+            features: HashMap::new(),
+            weights: vec![0.0; table.row() * table.col()],
+            sentences: vec![],
+            skipped: 0,
+        };
+        for sentence in &corpus.sentences {
+            match trainer.sentence(sentence) {
+                Some(sentence) => trainer.sentences.push(sentence),
+                None => trainer.skipped += 1,
+            }
+        }
+        trainer
+    }
+
+    /// Returns the number of sentences whose annotation is not found in the lattice.
+    /// Such sentences are not used for training.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    // sentence builds the lattice of an annotated sentence and finds the annotated path in it.
+    // Each word matches the node of the same span that shares the longest prefix of features.
+    fn sentence(&mut self, morphemes: &[Morpheme]) -> Option<Sentence> {
+        let input = morphemes
+            .iter()
+            .map(|m| m.surface.as_str())
+            .collect::<String>();
+        let char_len = input.chars().count();
+        let dummy = Node {
+            left_id: 0,
+            right_id: 0,
+            base: 0.0,
+            features: vec![],
+            previous: vec![],
+        };
+        // spans[i] is the start and end character positions of nodes[i]
+        let mut nodes = vec![dummy.clone()];
+        let mut spans = vec![(0, 0)];
+        let mut features = vec![vec![]];
+        let mut ends = vec![vec![]; char_len + 2];
+        ends[0].push(0);
+        // lookups are sorted by their start positions, so every node ending at the start of a node precedes it
+        for lookup in self.dict.lookup(&input) {
+            let start = lookup.char_pos;
+            let end = start + lookup.entry.surface.chars().count();
+            let keys = unigram_keys(
+                lookup.class,
+                lookup.entry.id,
+                &lookup.entry.features,
+                self.config.pos_depth,
+            );
+            let ids = keys.into_iter().map(|key| self.feature_id(key)).collect();
+            ends[end].push(nodes.len());
+            nodes.push(Node {
+                left_id: lookup.entry.morph.left_id as usize,
+                right_id: lookup.entry.morph.right_id as usize,
+                base: -lookup.entry.morph.cost as f64 / self.config.cost_factor,
+                features: ids,
+                previous: ends[start].clone(),
+            });
+            spans.push((start, end));
+            features.push(lookup.entry.features);
+        }
+        nodes.push(Node {
+            previous: ends[char_len].clone(),
+            ..dummy
+        });
+
+        let mut gold = Vec::with_capacity(morphemes.len() + 1);
+        let mut start = 0;
+        for morpheme in morphemes {
+            let end = start + morpheme.surface.chars().count();
+            let common_prefix = |i: usize| {
+                features[i]
+                    .iter()
+                    .zip(&morpheme.features)
+                    .take_while(|(a, b)| **a == b.as_str())
+                    .count()
+            };
+            // `max_by_key` returns the last maximum, so candidates are reversed to prefer the first one
+            let i = (1..spans.len())
+                .rev()
+                .filter(|&i| spans[i] == (start, end))
+                .max_by_key(|&i| common_prefix(i))?;
+            gold.push(i);
+            start = end;
+        }
+        gold.push(nodes.len() - 1);
+        Some(Sentence { nodes, gold })
+    }
+
+    fn feature_id(&mut self, key: String) -> usize {
+        let next = self.weights.len();
+        let id = *self.features.entry(key).or_insert(next);
+        if id == next {
+            self.weights.push(0.0);
+        }
+        id
+    }
+
+    fn bigram_id(&self, previous: &Node, target: &Node) -> usize {
+        self.dict.connection_table.row() * target.left_id + previous.right_id
+    }
+
+    fn node_score(&self, node: &Node) -> f64 {
+        node.base + node.features.iter().map(|&f| self.weights[f]).sum::<f64>()
+    }
+
+    fn edge_score(&self, previous: &Node, target: &Node) -> f64 {
+        let cost = self
+            .dict
+            .connection_table
+            .get(previous.right_id, target.left_id);
+        -cost as f64 / self.config.cost_factor + self.weights[self.bigram_id(previous, target)]
+    }
+
+    // accumulate adds the gradient of the negative log-likelihood of a sentence and returns the likelihood.
+    fn accumulate(&self, sentence: &Sentence, gradient: &mut [f64]) -> f64 {
+        let nodes = &sentence.nodes;
+        let node_scores = nodes.iter().map(|n| self.node_score(n)).collect::<Vec<_>>();
+
+        // alpha includes the score of the node itself and beta doesn't
+        let mut alpha = vec![f64::NEG_INFINITY; nodes.len()];
+        alpha[0] = 0.0;
+        for (i, node) in nodes.iter().enumerate().skip(1) {
+            for &j in &node.previous {
+                let score = alpha[j] + self.edge_score(&nodes[j], node);
+                alpha[i] = log_add_exp(alpha[i], score);
+            }
+            alpha[i] += node_scores[i];
+        }
+        let eos = nodes.len() - 1;
+        let mut beta = vec![f64::NEG_INFINITY; nodes.len()];
+        beta[eos] = 0.0;
+        for (i, node) in nodes.iter().enumerate().skip(1).rev() {
+            for &j in &node.previous {
+                let score = self.edge_score(&nodes[j], node) + node_scores[i] + beta[i];
+                beta[j] = log_add_exp(beta[j], score);
+            }
+        }
+
+        let z = alpha[eos];
+        for (i, node) in nodes.iter().enumerate().skip(1) {
+            let probability = (alpha[i] + beta[i] - z).exp();
+            for &f in &node.features {
+                gradient[f] += probability;
+            }
+            for &j in &node.previous {
+                let score = alpha[j] + self.edge_score(&nodes[j], node) + node_scores[i] + beta[i];
+                gradient[self.bigram_id(&nodes[j], node)] += (score - z).exp();
+            }
+        }
+
+        let mut gold_score = 0.0;
+        let mut previous = 0;
+        for &i in &sentence.gold {
+            gold_score += self.edge_score(&nodes[previous], &nodes[i]) + node_scores[i];
+            for &f in &nodes[i].features {
+                gradient[f] -= 1.0;
+            }
+            gradient[self.bigram_id(&nodes[previous], &nodes[i])] -= 1.0;
+            previous = i;
+        }
+        z - gold_score
+    }
+
+    /// Trains the weights with AdaGrad and returns the objective of each epoch.
+    pub fn train(&mut self) -> Vec<f64> {
+        let c = self.config.regularization;
+        let mut squares = vec![0.0; self.weights.len()];
+        let mut objectives = Vec::with_capacity(self.config.epochs);
+        for _ in 0..self.config.epochs {
+            let mut gradient = self.weights.iter().map(|w| w / c).collect::<Vec<_>>();
+            let mut objective = self.weights.iter().map(|w| w * w).sum::<f64>() / (2.0 * c);
+            for sentence in &self.sentences {
+                objective += self.accumulate(sentence, &mut gradient);
+            }
+            for ((weight, square), g) in self.weights.iter_mut().zip(&mut squares).zip(gradient) {
+                *square += g * g;
+                if *square > 0.0 {
+                    *weight -= self.config.learning_rate * g / square.sqrt();
+                }
+            }
+            objectives.push(objective);
+        }
+        objectives
+    }
+
+    fn cost(&self, base: i16, score: f64) -> i16 {
+        (base as f64 - self.config.cost_factor * score)
+            .round()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+    }
+
+    fn morphs(
+        &self,
+        class: EntryClass,
+        morphs: &Morphs,
+        features: impl Fn(KeywordID) -> Vec<&'a str>,
+    ) -> Morphs {
+        Morphs::from(
+            morphs
+                .iter()
+                .enumerate()
+                .map(|(i, morph)| {
+                    let id = i as KeywordID + 1;
+                    let score = unigram_keys(class, id, &features(id), self.config.pos_depth)
+                        .iter()
+                        .filter_map(|key| self.features.get(key))
+                        .map(|&f| self.weights[f])
+                        .sum();
+                    Morph::new(morph.left_id, morph.right_id, self.cost(morph.cost, score))
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Returns the seed dictionary with the trained word and connection costs.
+    /// It can be written as MeCab source files by `export::export`.
+    pub fn trained_dict(&self) -> Dict {
+        let table = &self.dict.connection_table;
+        let (row, col) = (table.row(), table.col());
+        let mut data = vec![0; row * col];
+        for left_id in 0..col {
+            for right_id in 0..row {
+                let i = row * left_id + right_id;
+                data[i] = self.cost(table.get(right_id, left_id), self.weights[i]);
+            }
+        }
+
+        let mut dict = self.dict.clone();
+        dict.morphs = self.morphs(EntryClass::Known, &self.dict.morphs, |id| {
+            self.dict
+                .morph_feature_table
+                .features(id)
+                .unwrap_or_default()
+        });
+        dict.unk_dict.morphs = self.morphs(EntryClass::Unknown, &self.dict.unk_dict.morphs, |id| {
+            self.dict
+                .unk_dict
+                .morph_feature_table
+                .features(id)
+                .unwrap_or_default()
+        });
+        dict.connection_table = ConnectionTable::from(MatrixDef { row, col, data });
+        dict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::{DictionaryBuilder, config::Config},
+        export,
+        testutil::TempDir,
+    };
+
+    const CHAR_DEF: &str = "\
+DEFAULT 0 1 0
+HIRAGANA 0 1 0
+0x3041..0x309F HIRAGANA
+";
+    const MATRIX_DEF: &str = "3 3\n0 0 0\n0 1 0\n0 2 0\n1 0 0\n1 1 0\n1 2 0\n2 0 0\n2 1 0\n2 2 0\n";
+    const UNK_DEF: &str = "\
+DEFAULT,1,1,10000,記号,一般,*,*,*,*,*
+HIRAGANA,1,1,10000,名詞,一般,*,*,*,*,*
+";
+    const LEXICON_CSV: &str = "\
+すもも,1,1,3000,名詞,一般,*,*,*,*,すもも,スモモ,スモモ
+す,1,1,1000,名詞,一般,*,*,*,*,す,ス,ス
+もも,1,1,1000,名詞,一般,*,*,*,*,もも,モモ,モモ
+も,2,2,1000,助詞,係助詞,*,*,*,*,も,モ,モ
+";
+    const CORPUS: &str = "\
+すもも\t名詞,一般,*,*,*,*,すもも,スモモ,スモモ
+EOS
+すもも\t名詞,一般,*,*,*,*,すもも,スモモ,スモモ
+も\t助詞,係助詞,*,*,*,*,も,モ,モ
+もも\t名詞,一般,*,*,*,*,もも,モモ,モモ
+EOS
+すも\t名詞,一般,*,*,*,*,*,*,*
+EOS
+";
+
+    #[test]
+    fn test_parse_corpus() {
+        let corpus = Corpus::parse(CORPUS).unwrap();
+        assert_eq!(corpus.sentences.len(), 3);
+        assert_eq!(corpus.sentences[1][1].surface, "も");
+        assert_eq!(corpus.sentences[1][1].features[..2], ["助詞", "係助詞"]);
+
+        assert!(Corpus::parse("すもも 名詞\nEOS\n").is_err());
+        assert!(Corpus::parse("すもも\t名詞\n").is_err());
+    }
+
+    #[test]
+    fn test_train() {
+        let src = TempDir::new("kanpyo-train-test-src");
+        let out = TempDir::new("kanpyo-train-test-out");
+        let src = src.path();
+        let out = out.path();
+        fs::write(src.join("char.def"), CHAR_DEF).unwrap();
+        fs::write(src.join("matrix.def"), MATRIX_DEF).unwrap();
+        fs::write(src.join("unk.def"), UNK_DEF).unwrap();
+        fs::write(src.join("lexicon.csv"), LEXICON_CSV).unwrap();
+        let config = Config::new(src, encoding_rs::UTF_8);
+        let dict = DictionaryBuilder::from_config(&config).expect("Failed to build dict");
+
+        let corpus = Corpus::parse(CORPUS).unwrap();
+        let mut trainer = Trainer::new(&dict, &corpus, TrainConfig::default());
+        // すも is neither a known word nor an unknown word
+        assert_eq!(trainer.skipped(), 1);
+        let objectives = trainer.train();
+        assert!(objectives.last().unwrap() < objectives.first().unwrap());
+
+        let trained = trainer.trained_dict();
+        let cost = |surface: &str| {
+            trained
+                .entries()
+                .find(|entry| entry.surface == surface)
+                .unwrap()
+                .morph
+                .cost as i32
+        };
+        let connection = |right_id: usize, left_id: usize| {
+            trained.connection_table.get(right_id, left_id) as i32
+        };
+        // the seed dictionary prefers す/もも to すもも
+        let sumomo = connection(0, 1) + cost("すもも") + connection(1, 0);
+        let su_momo =
+            connection(0, 1) + cost("す") + connection(1, 1) + cost("もも") + connection(1, 0);
+        assert!(sumomo < su_momo);
+        // BOS never precedes EOS, so the connection keeps its cost
+        assert_eq!(connection(0, 0), 0);
+
+        export::export(&trained, &Config::new(out, encoding_rs::UTF_8)).unwrap();
+        let rebuilt = DictionaryBuilder::from_config(&Config::new(out, encoding_rs::UTF_8))
+            .expect("Failed to build trained dict");
+        assert_eq!(trained, rebuilt);
+    }
+}
@@ -180,6 +180,63 @@ impl DoubleArray {
             Some(id_and_byte_lengths)
         }
     }
+
+    // search_predictive_of finds keywords starting with a prefix and returns the ids and keywords in lexicographic order.
+    // At most `limit` keywords are returned.
+    pub fn search_predictive_of(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Option<Vec<(KeywordID, String)>> {
+        let mut p = ROOT_ID as i32;
+        for ch in prefix.bytes() {
+            let q = self.0.get(p as usize)?.base + ch as i32;
+            if q < 0 || self.0.get(q as usize)?.check != p {
+                return None;
+            }
+            p = q;
+        }
+
+        let mut ids_and_keywords = vec![];
+        // depth-first search in byte order, so the terminator (0) of a keyword comes before its extensions
+        let mut stack = vec![(p, prefix.as_bytes().to_vec())];
+        while let Some((p, keyword)) = stack.pop() {
+            if ids_and_keywords.len() >= limit {
+                break;
+            }
+            let base = self.0[p as usize].base;
+            let mut children = vec![];
+            for ch in 0..=u8::MAX {
+                let q = base + ch as i32;
+                if q <= 0 {
+                    continue;
+                }
+                match self.0.get(q as usize) {
+                    Some(node) if node.check == p => children.push((q, ch)),
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            for &(q, ch) in children.iter().rev() {
+                if ch == TERMINATOR {
+                    continue;
+                }
+                let mut keyword = keyword.clone();
+                keyword.push(ch);
+                stack.push((q, keyword));
+            }
+            if let Some(&(q, _)) = children.first().filter(|&&(_, ch)| ch == TERMINATOR) {
+                let id = -self.0[q as usize].base as KeywordID;
+                let keyword = String::from_utf8(keyword).expect("keyword must be valid UTF-8");
+                ids_and_keywords.push((id, keyword));
+            }
+        }
+        if ids_and_keywords.is_empty() {
+            None
+        } else {
+            Some(ids_and_keywords)
+        }
+    }
 }
 
 impl Default for DoubleArray {
@@ -322,6 +379,57 @@ mod tests {
         assert_eq!(ids_and_lengths, None);
     }
 
+    #[test]
+    fn test_search_predictive() {
+        let sorted_keywords = vec![
+            "早稲田",
+            "早稲田大学",
+            "東京",
+            "東京大学",
+            "東京大学大学院",
+            "東京工業大学",
+            "東北",
+        ]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+        let da = build(&sorted_keywords).expect("failed to build");
+
+        let results = da.search_predictive_of("東京", usize::MAX);
+        let expecteds = vec![
+            (3, "東京".to_string()),
+            (4, "東京大学".to_string()),
+            (5, "東京大学大学院".to_string()),
+            (6, "東京工業大学".to_string()),
+        ];
+        assert_eq!(results, Some(expecteds));
+
+        // the prefix doesn't have to be a keyword itself
+        let results = da.search_predictive_of("東", usize::MAX);
+        assert_eq!(
+            results.map(|v| v.into_iter().map(|(id, _)| id).collect::<Vec<_>>()),
+            Some(vec![3, 4, 5, 6, 7])
+        );
+
+        // all keywords in lexicographic order
+        let results = da.search_predictive_of("", usize::MAX);
+        assert_eq!(
+            results.map(|v| v.into_iter().map(|(_, k)| k).collect::<Vec<_>>()),
+            Some(sorted_keywords.clone())
+        );
+
+        let results = da.search_predictive_of("東京", 2);
+        let expecteds = vec![(3, "東京".to_string()), (4, "東京大学".to_string())];
+        assert_eq!(results, Some(expecteds));
+
+        assert_eq!(da.search_predictive_of("大学", usize::MAX), None);
+        assert_eq!(
+            da.search_predictive_of("東京大学大学院生", usize::MAX),
+            None
+        );
+        assert_eq!(da.search_predictive_of("東京", 0), None);
+    }
+
     #[test]
     fn test_build_and_search_multibyte() {
         let mut keywords = vec!["12345", "2345", "１２３", "abc", "ABCD", "あいう", "Ａ"];
@@ -0,0 +1,244 @@
+//! Python bindings of Kanpyo.
+
+use std::sync::Arc;
+
+use kanpyo::token::{Token as KanpyoToken, TokenClass};
+use kanpyo_dict::{
+    builder::{DictionaryBuilder, config::Config},
+    dict::Dict,
+    error::KanpyoError,
+};
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+};
+
+fn to_py_err(e: KanpyoError) -> PyErr {
+    match e {
+        KanpyoError::Io(e) => PyIOError::new_err(e.to_string()),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// A dictionary built by kanpyo.
+/// It holds a tokenizer of the dictionary, which tokenizers created from it share.
+#[pyclass(module = "kanpyo", frozen)]
+#[derive(Clone)]
+struct Dictionary(Arc<kanpyo::tokenizer::Tokenizer>);
+
+impl From<Dict> for Dictionary {
+    fn from(dict: Dict) -> Self {
+        Dictionary(Arc::new(kanpyo::tokenizer::Tokenizer::new(dict)))
+    }
+}
+
+#[pymethods]
+impl Dictionary {
+    /// Loads a dictionary from a file.
+    #[staticmethod]
+    fn load(path: std::path::PathBuf) -> PyResult<Self> {
+        let file = std::fs::File::open(&path)?;
+        Dict::load(&mut std::io::BufReader::new(file))
+            .map(Dictionary::from)
+            .map_err(to_py_err)
+    }
+
+    /// Loads a dictionary from bytes.
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        Dict::load(&mut std::io::Cursor::new(data))
+            .map(Dictionary::from)
+            .map_err(to_py_err)
+    }
+
+    /// Builds a dictionary from a directory of MeCab dictionary sources like
+    /// char.def, unk.def, matrix.def and lexicon CSV files.
+    #[staticmethod]
+    #[pyo3(signature = (path, encoding = "euc-jp"))]
+    fn build(path: std::path::PathBuf, encoding: &str) -> PyResult<Self> {
+        let encoding = encoding_rs::Encoding::for_label(encoding.as_bytes())
+            .ok_or_else(|| PyValueError::new_err(format!("unknown encoding: {}", encoding)))?;
+        DictionaryBuilder::from_config(&Config::new(&path, encoding))
+            .map(Dictionary::from)
+            .map_err(to_py_err)
+    }
+
+    /// Loads the MeCab IPA dictionary embedded by the `mecab-ipadic` feature.
+    #[staticmethod]
+    fn ipadic() -> PyResult<Self> {
+        #[cfg(feature = "mecab-ipadic")]
+        {
+            kanpyo::embedded::mecab_ipadic()
+                .map(Dictionary::from)
+                .map_err(to_py_err)
+        }
+        #[cfg(not(feature = "mecab-ipadic"))]
+        {
+            Err(pyo3::exceptions::PyRuntimeError::new_err(
+                "kanpyo is built without the mecab-ipadic feature",
+            ))
+        }
+    }
+
+    /// Saves the dictionary to a file, which `Dictionary.load` loads.
+    fn save(&self, path: std::path::PathBuf) -> PyResult<()> {
+        let mut file = std::fs::File::create(&path)?;
+        self.0.dict.build(&mut file).map_err(to_py_err)
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.dict.morphs.len()
+    }
+}
+
+/// A token with offsets into the input and features.
+#[pyclass(module = "kanpyo", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct Token {
+    /// Surface form.
+    surface: String,
+    /// Byte offset in the input in UTF-8.
+    position: usize,
+    /// Char offset of the beginning in the input.
+    start: usize,
+    /// Char offset of the end in the input.
+    end: usize,
+    /// `known` or `unknown`.
+    token_class: &'static str,
+    /// Features like `["名詞", "固有名詞", "地域", "一般", "*", "*", "東京", "トウキョウ", "トーキョー"]`.
+    features: Vec<String>,
+}
+
+impl Token {
+    fn new(token: &KanpyoToken, dict: &Dict) -> Self {
+        Token {
+            surface: token.surface.clone(),
+            position: token.position,
+            start: token.start,
+            end: token.end,
+            token_class: match token.class {
+                TokenClass::Unknown => "unknown",
+                _ => "known",
+            },
+            features: token
+                .features(dict)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+
+    // feature returns the i-th feature unless it's missing or `*`.
+    fn feature(&self, i: usize) -> Option<String> {
+        self.features
+            .get(i)
+            .filter(|feature| *feature != "*")
+            .cloned()
+    }
+}
+
+#[pymethods]
+impl Token {
+    /// Part-of-speech, which is the first four features of IPADIC.
+    #[getter]
+    fn pos(&self) -> Vec<String> {
+        self.features.iter().take(4).cloned().collect()
+    }
+
+    #[getter]
+    fn conjugation_type(&self) -> Option<String> {
+        self.feature(4)
+    }
+
+    #[getter]
+    fn conjugation_form(&self) -> Option<String> {
+        self.feature(5)
+    }
+
+    #[getter]
+    fn base_form(&self) -> Option<String> {
+        self.feature(6)
+    }
+
+    #[getter]
+    fn reading(&self) -> Option<String> {
+        self.feature(7)
+    }
+
+    #[getter]
+    fn pronunciation(&self) -> Option<String> {
+        self.feature(8)
+    }
+
+    fn __len__(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn __str__(&self) -> String {
+        format!("{}\t{}", self.surface, self.features.join(","))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Token(surface={:?}, start={}, end={}, features={:?})",
+            self.surface,
+            self.start,
+            self.end,
+            self.features.join(",")
+        )
+    }
+}
+
+/// A tokenizer with a dictionary.
+#[pyclass(module = "kanpyo", frozen)]
+struct Tokenizer(Arc<kanpyo::tokenizer::Tokenizer>);
+
+impl Tokenizer {
+    fn tokens(&self, input: &str) -> Vec<Token> {
+        let dict = &self.0.dict;
+        self.0
+            .tokenize(input)
+            .iter()
+            .filter(|token| token.class != TokenClass::Dummy)
+            .map(|token| Token::new(token, dict))
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Tokenizer {
+    /// Creates a tokenizer sharing the dictionary without copying it.
+    #[new]
+    fn new(dictionary: &Dictionary) -> Self {
+        Tokenizer(Arc::clone(&dictionary.0))
+    }
+
+    /// Tokenizes text into tokens, excluding BOS and EOS.
+    fn tokenize(&self, py: Python<'_>, text: &str) -> Vec<Token> {
+        py.allow_threads(|| self.tokens(text))
+    }
+
+    /// Tokenizes texts without holding the GIL, so other threads can run meanwhile.
+    fn tokenize_batch(&self, py: Python<'_>, texts: Vec<String>) -> Vec<Vec<Token>> {
+        py.allow_threads(|| texts.iter().map(|text| self.tokens(text)).collect())
+    }
+
+    /// Returns the surfaces of the tokens.
+    fn wakati(&self, py: Python<'_>, text: &str) -> Vec<String> {
+        py.allow_threads(|| {
+            self.tokens(text)
+                .into_iter()
+                .map(|token| token.surface)
+                .collect()
+        })
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "kanpyo")]
+fn kanpyo_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Dictionary>()?;
+    m.add_class::<Token>()?;
+    m.add_class::<Tokenizer>()?;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+from pathlib import Path
+
+import pytest
+
+import kanpyo
+
+# the sources of the test dictionary shared with the Rust tests
+DICT_DIR = Path(__file__).resolve().parents[2] / "kanpyo-dict" / "testdata" / "dict"
+
+
+@pytest.fixture(scope="session")
+def dictionary():
+    return kanpyo.Dictionary.build(DICT_DIR, encoding="utf-8")
+
+
+@pytest.fixture(scope="session")
+def tokenizer(dictionary):
+    return kanpyo.Tokenizer(dictionary)
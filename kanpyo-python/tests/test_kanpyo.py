@@ -0,0 +1,85 @@
+import threading
+
+import pytest
+
+import kanpyo
+
+
+def test_tokenize(tokenizer):
+    tokens = tokenizer.tokenize("東京都へ行く")
+    assert [token.surface for token in tokens] == ["東京", "都", "へ", "行く"]
+    assert [token.position for token in tokens] == [0, 6, 9, 12]
+    assert [(token.start, token.end) for token in tokens] == [(0, 2), (2, 3), (3, 4), (4, 6)]
+    assert [token.token_class for token in tokens] == ["known", "known", "known", "known"]
+    assert [token.token_class for token in tokenizer.tokenize("猫")] == ["unknown"]
+    assert tokenizer.tokenize("") == []
+
+
+def test_offsets(tokenizer):
+    text = "東京 都"
+    for token in tokenizer.tokenize(text):
+        assert text[token.start : token.end] == token.surface
+        assert text.encode()[token.position :].decode().startswith(token.surface)
+
+
+def test_features(tokenizer):
+    tokyo, _, _, iku = tokenizer.tokenize("東京都へ行く")
+    (unknown,) = tokenizer.tokenize("猫")
+    assert tokyo.features == ["名詞", "固有名詞", "地域", "一般", "*", "*", "東京", "トウキョウ", "トーキョー"]
+    assert tokyo.pos == ["名詞", "固有名詞", "地域", "一般"]
+    assert tokyo.conjugation_type is None
+    assert tokyo.base_form == "東京"
+    assert tokyo.reading == "トウキョウ"
+    assert tokyo.pronunciation == "トーキョー"
+    assert iku.conjugation_type == "五段・カ行促音便"
+    assert iku.conjugation_form == "基本形"
+    assert unknown.base_form is None
+    assert unknown.reading is None
+    assert str(tokyo) == "東京\t名詞,固有名詞,地域,一般,*,*,東京,トウキョウ,トーキョー"
+    assert "東京" in repr(tokyo)
+    assert len(tokyo) == 2
+
+
+def test_tokenize_batch(tokenizer):
+    texts = ["東京都", "行く", ""] * 100
+    batches = tokenizer.tokenize_batch(texts)
+    assert len(batches) == len(texts)
+    assert [[token.surface for token in tokens] for tokens in batches[:3]] == [["東京", "都"], ["行く"], []]
+
+
+def test_threads(tokenizer):
+    results = [None] * 4
+
+    def run(i):
+        results[i] = tokenizer.tokenize_batch(["東京都へ行く"] * 100)
+
+    threads = [threading.Thread(target=run, args=(i,)) for i in range(len(results))]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+    assert all(len(result) == 100 for result in results)
+
+
+def test_wakati(tokenizer):
+    assert tokenizer.wakati("東京都へ行く") == ["東京", "都", "へ", "行く"]
+
+
+def test_dictionary(dictionary, tmp_path):
+    assert len(dictionary) == 6
+    path = tmp_path / "test.dict"
+    dictionary.save(path)
+    for loaded in [kanpyo.Dictionary.load(path), kanpyo.Dictionary.from_bytes(path.read_bytes())]:
+        assert len(loaded) == 6
+        assert [token.surface for token in kanpyo.Tokenizer(loaded).tokenize("東京都")] == ["東京", "都"]
+
+
+def test_dictionary_errors(tmp_path):
+    with pytest.raises(OSError):
+        kanpyo.Dictionary.load(tmp_path / "missing.dict")
+    with pytest.raises(ValueError):
+        kanpyo.Dictionary.from_bytes(b"broken")
+    with pytest.raises(ValueError):
+        kanpyo.Dictionary.build(tmp_path, encoding="unknown")
+    with pytest.raises(OSError):
+        kanpyo.Dictionary.build(tmp_path / "missing", encoding="utf-8")
@@ -0,0 +1,294 @@
+//! Kanpyo tokenizer for [tantivy](https://github.com/quickwit-oss/tantivy).
+//!
+//! ```ignore
+//! let tokenizer = KanpyoTokenizer::new(Tokenizer::new(dict)).with_stop_pos(["助詞", "記号"]);
+//! index.tokenizers().register("kanpyo", tokenizer);
+//! ```
+
+use std::sync::Arc;
+
+use kanpyo::{
+    filter::{matches_pos_prefix, parse_pos_prefix},
+    token::{Token as KanpyoToken, TokenClass},
+    tokenizer::Tokenizer,
+};
+use tantivy_tokenizer_api::{Token, TokenStream};
+
+/// KanpyoTokenizer is a tantivy tokenizer sharing a kanpyo tokenizer.
+///
+/// Tokens carry byte offsets into the text and positions incremented per word.
+/// Whitespace is skipped, and tokens can be filtered by POS prefixes like `名詞,固有名詞`.
+/// Removed tokens still take positions, so phrase queries don't match across them.
+#[derive(Clone)]
+pub struct KanpyoTokenizer {
+    tokenizer: Arc<Tokenizer>,
+    stop_pos: Vec<Vec<String>>,
+    keep_pos: Vec<Vec<String>>,
+}
+
+impl KanpyoTokenizer {
+    pub fn new(tokenizer: Tokenizer) -> Self {
+        Self::from_arc(Arc::new(tokenizer))
+    }
+
+    /// Shares a tokenizer with other tokenizers or threads without copying the dictionary.
+    pub fn from_arc(tokenizer: Arc<Tokenizer>) -> Self {
+        KanpyoTokenizer {
+            tokenizer,
+            stop_pos: Vec::new(),
+            keep_pos: Vec::new(),
+        }
+    }
+
+    /// Removes tokens whose POS starts with any of comma-separated prefixes, e.g. `助詞` or `名詞,数`.
+    pub fn with_stop_pos<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, prefixes: I) -> Self {
+        self.stop_pos
+            .extend(prefixes.into_iter().map(|p| parse_pos_prefix(p.as_ref())));
+        self
+    }
+
+    /// Keeps only tokens whose POS starts with any of comma-separated prefixes.
+    /// Stop POS are applied after it.
+    pub fn with_keep_pos<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, prefixes: I) -> Self {
+        self.keep_pos
+            .extend(prefixes.into_iter().map(|p| parse_pos_prefix(p.as_ref())));
+        self
+    }
+
+    fn is_kept(&self, token: &KanpyoToken) -> bool {
+        if self.stop_pos.is_empty() && self.keep_pos.is_empty() {
+            return true;
+        }
+        let features = token.features(&self.tokenizer.dict);
+        (self.keep_pos.is_empty()
+            || self
+                .keep_pos
+                .iter()
+                .any(|p| matches_pos_prefix(&features, p)))
+            && !self
+                .stop_pos
+                .iter()
+                .any(|p| matches_pos_prefix(&features, p))
+    }
+}
+
+impl tantivy_tokenizer_api::Tokenizer for KanpyoTokenizer {
+    type TokenStream<'a> = KanpyoTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        let mut position = 0;
+        let mut tokens = Vec::new();
+        for token in self.tokenizer.tokenize(text) {
+            if token.class == TokenClass::Dummy || token.surface.trim().is_empty() {
+                continue;
+            }
+            if self.is_kept(&token) {
+                // the surface may be normalized, so the offsets come from the text
+                let range = token.byte_range(text);
+                tokens.push(Token {
+                    offset_from: range.start,
+                    offset_to: range.end,
+                    position,
+                    text: token.surface,
+                    position_length: 1,
+                });
+            }
+            position += 1;
+        }
+        KanpyoTokenStream {
+            tokens,
+            index: None,
+            current: Token::default(),
+        }
+    }
+}
+
+/// KanpyoTokenStream is a stream of tokens given by `KanpyoTokenizer`.
+pub struct KanpyoTokenStream {
+    tokens: Vec<Token>,
+    index: Option<usize>,
+    // the current token, which may be modified by filters
+    current: Token,
+}
+
+impl TokenStream for KanpyoTokenStream {
+    fn advance(&mut self) -> bool {
+        let index = self.index.map_or(0, |i| i + 1);
+        self.index = Some(index);
+        match self.tokens.get_mut(index) {
+            Some(token) => {
+                self.current = std::mem::take(token);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.current
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kanpyo::normalize::Normalizer;
+    use std::sync::OnceLock;
+    use tantivy::{
+        Index, TantivyDocument,
+        collector::TopDocs,
+        query::QueryParser,
+        schema::{IndexRecordOption, STORED, Schema, TextFieldIndexing, TextOptions, Value},
+        tokenizer::{LowerCaser, TextAnalyzer},
+    };
+    use tantivy_tokenizer_api::Tokenizer as _;
+
+    // new_tokenizer shares a tokenizer since tests run in parallel.
+    fn new_tokenizer() -> Arc<Tokenizer> {
+        static TOKENIZER: OnceLock<Arc<Tokenizer>> = OnceLock::new();
+        Arc::clone(TOKENIZER.get_or_init(|| Arc::new(build_tokenizer())))
+    }
+
+    fn build_tokenizer() -> Tokenizer {
+        Tokenizer::new(kanpyo_dict::testutil::test_dict())
+    }
+
+    fn collect(tokenizer: &mut KanpyoTokenizer, text: &str) -> Vec<Token> {
+        let mut stream = tokenizer.token_stream(text);
+        let mut tokens = Vec::new();
+        stream.process(&mut |token| tokens.push(token.clone()));
+        tokens
+    }
+
+    #[test]
+    fn test_token_stream() {
+        let mut tokenizer = KanpyoTokenizer::from_arc(new_tokenizer());
+        let text = "東京 都へ行く。";
+        let tokens = collect(&mut tokenizer, text);
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.text.as_str(), t.position, t.offset_from, t.offset_to))
+                .collect::<Vec<_>>(),
+            vec![
+                ("東京", 0, 0, 6),
+                ("都", 1, 7, 10),
+                ("へ", 2, 10, 13),
+                ("行く", 3, 13, 19),
+                ("。", 4, 19, 22)
+            ]
+        );
+        for token in &tokens {
+            assert_eq!(&text[token.offset_from..token.offset_to], token.text);
+        }
+        assert!(collect(&mut tokenizer, "").is_empty());
+    }
+
+    #[test]
+    fn test_token_stream_with_normalizer() {
+        let mut tokenizer =
+            KanpyoTokenizer::new(build_tokenizer().with_normalizer(Normalizer::new()));
+        let text = "㍿東京㍿";
+        let tokens = collect(&mut tokenizer, text);
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.text.as_str(), t.offset_from, t.offset_to))
+                .collect::<Vec<_>>(),
+            vec![("株式会社", 0, 3), ("東京", 3, 9), ("株式会社", 9, 12)]
+        );
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| &text[t.offset_from..t.offset_to])
+                .collect::<Vec<_>>(),
+            vec!["㍿", "東京", "㍿"]
+        );
+    }
+
+    #[test]
+    fn test_pos_filter() {
+        let mut tokenizer =
+            KanpyoTokenizer::from_arc(new_tokenizer()).with_stop_pos(["助詞", "記号"]);
+        let tokens = collect(&mut tokenizer, "東京都へ行く。");
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|t| (t.text.as_str(), t.position))
+                .collect::<Vec<_>>(),
+            vec![("東京", 0), ("都", 1), ("行く", 3)]
+        );
+
+        let mut tokenizer = KanpyoTokenizer::from_arc(new_tokenizer())
+            .with_keep_pos(["名詞"])
+            .with_stop_pos(["名詞,接尾"]);
+        let tokens = collect(&mut tokenizer, "東京都へ行く。");
+        assert_eq!(
+            tokens.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(),
+            vec!["東京"]
+        );
+    }
+
+    #[test]
+    fn test_index() {
+        let indexing = TextFieldIndexing::default()
+            .set_tokenizer("kanpyo")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions);
+        let mut schema = Schema::builder();
+        let body = schema.add_text_field(
+            "body",
+            TextOptions::default()
+                .set_indexing_options(indexing)
+                .set_stored(),
+        );
+        let id = schema.add_u64_field("id", STORED);
+        let index = Index::create_in_ram(schema.build());
+        index.tokenizers().register(
+            "kanpyo",
+            TextAnalyzer::builder(
+                KanpyoTokenizer::from_arc(new_tokenizer()).with_stop_pos(["記号"]),
+            )
+            .filter(LowerCaser)
+            .build(),
+        );
+
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (i, text) in ["東京都へ行く。", "都へ行く", "東京へ行く"]
+            .iter()
+            .enumerate()
+        {
+            let mut doc = TantivyDocument::default();
+            doc.add_text(body, text);
+            doc.add_u64(id, i as u64);
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let parser = QueryParser::for_index(&index, vec![body]);
+        let search = |query: &str| {
+            let mut ids = searcher
+                .search(
+                    &parser.parse_query(query).unwrap(),
+                    &TopDocs::with_limit(10),
+                )
+                .unwrap()
+                .into_iter()
+                .map(|(_, address)| {
+                    let doc: TantivyDocument = searcher.doc(address).unwrap();
+                    doc.get_first(id).unwrap().as_u64().unwrap()
+                })
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(search("東京"), vec![0, 2]);
+        assert_eq!(search("都"), vec![0, 1]);
+        assert_eq!(search("\"東京都\""), vec![0]);
+        assert_eq!(search("\"東京へ\""), vec![2]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, path::Path};
+
+use kanpyo_dict::{
+    dict::Dict,
+    error::{KanpyoError, Result},
+};
+
+use crate::{
+    filter::{matches_pos_prefix, parse_pos_prefix},
+    lattice::{
+        cost::{CostModel, DictCostModel},
+        node::Node,
+    },
+};
+
+/// CostAdjustments adds cost deltas to the dictionary costs at runtime.
+/// A positive delta penalizes and a negative one boosts.
+///
+/// The file format is a whitespace-separated list of rules. Feature prefixes are comma-separated.
+///
+/// ```text
+/// # kind    pattern                   delta
+/// feature   接頭詞                    500
+/// feature   名詞,固有名詞             -200
+/// surface   東京                      -1000
+/// bigram    記号    記号              10000
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CostAdjustments {
+    /// Deltas added to the word cost of nodes whose features start with a prefix.
+    pub features: Vec<(Vec<String>, i32)>,
+    /// Deltas added to the word cost of nodes with a surface.
+    pub surfaces: HashMap<String, i32>,
+    /// Deltas added to the connection cost between nodes whose features start with prefixes.
+    pub bigrams: Vec<(Vec<String>, Vec<String>, i32)>,
+}
+
+impl CostAdjustments {
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty() && self.surfaces.is_empty() && self.bigrams.is_empty()
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut adjustments = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let invalid =
+                || KanpyoError::Parse(format!("invalid rule at line {}: {}", i + 1, line));
+            match fields.as_slice() {
+                ["feature", prefix, delta] => adjustments.features.push((
+                    parse_pos_prefix(prefix),
+                    delta.parse().map_err(|_| invalid())?,
+                )),
+                ["surface", surface, delta] => {
+                    adjustments
+                        .surfaces
+                        .insert(surface.to_string(), delta.parse().map_err(|_| invalid())?);
+                }
+                ["bigram", previous, next, delta] => adjustments.bigrams.push((
+                    parse_pos_prefix(previous),
+                    parse_pos_prefix(next),
+                    delta.parse().map_err(|_| invalid())?,
+                )),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(adjustments)
+    }
+}
+
+impl CostModel for CostAdjustments {
+    fn node_cost(&self, dict: &Dict, node: &Node) -> i32 {
+        let mut cost = DictCostModel.node_cost(dict, node);
+        if let Node::Known(word) | Node::Unknown(word) = node {
+            cost = cost.saturating_add(self.surfaces.get(&word.surface).copied().unwrap_or(0));
+        }
+        if !self.features.is_empty() {
+            let features = node.features(dict);
+            cost = self
+                .features
+                .iter()
+                .filter(|(prefix, _)| matches_pos_prefix(&features, prefix))
+                .fold(cost, |cost, (_, delta)| cost.saturating_add(*delta));
+        }
+        cost
+    }
+
+    fn edge_cost(&self, dict: &Dict, previous: &Node, target: &Node) -> i32 {
+        let cost = DictCostModel.edge_cost(dict, previous, target);
+        if self.bigrams.is_empty() {
+            return cost;
+        }
+        // the features of the target are looked up only if the previous node matches a bigram
+        let previous = previous.features(dict);
+        let mut bigrams = self
+            .bigrams
+            .iter()
+            .filter(|(p, _, _)| matches_pos_prefix(&previous, p))
+            .peekable();
+        if bigrams.peek().is_none() {
+            return cost;
+        }
+        let target = target.features(dict);
+        bigrams
+            .filter(|(_, t, _)| matches_pos_prefix(&target, t))
+            .fold(cost, |cost, (_, _, delta)| cost.saturating_add(*delta))
+    }
+}
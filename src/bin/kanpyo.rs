@@ -1,11 +1,22 @@
-use clap::{Parser, Subcommand, ValueEnum};
-use kanpyo::{lattice::node::BOS_EOS_ID, tokenizer::Tokenizer};
-use kanpyo_dict::dict;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use kanpyo::{
+    adjustment::CostAdjustments,
+    filter::{Filters, TokenFilter},
+    format::{Dicrc, Format, Template},
+    lattice::node::Node,
+    normalize::Normalizer,
+    sentence::SentenceSplitter,
+    tokenizer::Tokenizer,
+};
+use kanpyo_dict::{
+    dict,
+    estimate::Estimator,
+    train::{Corpus, TrainConfig, Trainer},
+};
+#[cfg(feature = "serde")]
+use std::io::Write;
 use std::path::PathBuf;
 
-#[cfg(feature = "mecab-ipadic")]
-static EMBEDDED_DICT: &[u8] = include_bytes!(env!("KANPYO_MECAB_IPADIC_PATH"));
-
 #[derive(Parser)]
 #[command(name = "kanpyo", about = "Japanese Morphological Analyzer", version = "0.1", long_about=None)]
 struct KanpyoCommand {
@@ -27,6 +38,28 @@ enum SubCommand {
         /// Custom dictionary
         #[arg(short, long)]
         custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
+        /// Output format
+        #[arg(
+            long,
+            value_enum,
+            default_value = "text",
+            conflicts_with = "FormatArgs"
+        )]
+        output: Output,
+        /// Token filters applied in order: stop-pos=<prefix>, base-form, katakana-stem[=<min length>] or width
+        #[arg(long = "filter", conflicts_with = "FormatArgs")]
+        filters: Vec<TokenFilter>,
+        /// Normalize input with NFKC before tokenization, keeping offsets into the input
+        #[arg(long, conflicts_with = "FormatArgs")]
+        normalize: bool,
+        /// Characters left as they are by --normalize, e.g. "～ｰ"
+        #[arg(long, requires = "normalize")]
+        normalize_keep: Option<String>,
+        #[command(flatten)]
+        format: FormatArgs,
     },
     /// Output lattice in Graphviz format
     Graphviz {
@@ -39,15 +72,305 @@ enum SubCommand {
         /// Custom dictionary
         #[arg(short, long)]
         custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
         /// Output full state of lattice
         #[arg(short, long, default_value = "false")]
         full_state: bool,
         /// DPI of output image
         #[arg(long, default_value = "48")]
         dpi: usize,
+        /// Number of best paths to highlight
+        #[arg(short, long, default_value = "1")]
+        nbest: usize,
+    },
+    /// Output lattice in various formats
+    Lattice {
+        /// Input text to analyze [default: stdin]
+        #[arg(index = 1)]
+        input: Option<String>,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "html")]
+        format: LatticeFormat,
+    },
+    /// Decode a lattice in JSON with the Viterbi algorithm
+    #[cfg(feature = "serde")]
+    Decode {
+        /// Lattice in JSON written by `kanpyo lattice --format json` [default: stdin]
+        #[arg(index = 1)]
+        input: Option<PathBuf>,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+    },
+    /// Serve tokenization over HTTP with a JSON API
+    #[cfg(feature = "server")]
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value = "8080")]
+        port: u16,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Number of worker threads
+        #[arg(short, long, default_value = "4")]
+        workers: usize,
+        /// Maximum number of paths of the n-best mode and lattices
+        #[arg(long, default_value = "100")]
+        max_nbest: usize,
+        /// Maximum size of a request body in bytes
+        #[arg(long, default_value = "1048576")]
+        max_body_size: usize,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
+    },
+    /// Serve newline-delimited JSON-RPC requests on stdin and write responses to stdout
+    #[cfg(feature = "serde")]
+    Rpc {
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
+    },
+    /// Restore the original text from the output of `kanpyo tokenize --output wakati`
+    Detokenize {
+        /// Space-separated tokens [default: stdin]
+        #[arg(index = 1)]
+        input: Option<String>,
+    },
+    /// Explain the cost breakdown of the best path
+    Explain {
+        /// Input text to analyze [default: stdin]
+        #[arg(index = 1)]
+        input: Option<String>,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Cost adjustments by feature prefix, surface or POS bigram
+        #[arg(short, long)]
+        adjustments: Option<PathBuf>,
+    },
+    /// List dictionary entries matching at each position of input text
+    Lookup {
+        /// Input text to look up [default: stdin]
+        #[arg(index = 1)]
+        input: Option<String>,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+    },
+    /// Manage dictionaries
+    Dict {
+        #[command(subcommand)]
+        subcommand: DictCommand,
+    },
+}
+
+// FormatArgs are MeCab compatible output format options.
+#[derive(Debug, Args)]
+struct FormatArgs {
+    /// Output format name, e.g. wakati, chasen, yomi or one defined in --dicrc
+    #[arg(short = 'O', long)]
+    output_format: Option<String>,
+    /// Format of known words, e.g. "%m\t%f[0]\n"
+    #[arg(short = 'F', long)]
+    node_format: Option<String>,
+    /// Format of unknown words [default: --node-format]
+    #[arg(short = 'U', long)]
+    unk_format: Option<String>,
+    /// Format of the beginning of a sentence
+    #[arg(short = 'B', long)]
+    bos_format: Option<String>,
+    /// Format of the end of a sentence
+    #[arg(short = 'E', long)]
+    eos_format: Option<String>,
+    /// dicrc of a MeCab dictionary defining output formats
+    #[arg(long)]
+    dicrc: Option<PathBuf>,
+}
+
+impl FormatArgs {
+    // is_wakati returns whether only -Owakati is given, which is the same as --output wakati.
+    fn is_wakati(&self) -> bool {
+        self.output_format.as_deref() == Some("wakati")
+            && self.node_format.is_none()
+            && self.unk_format.is_none()
+            && self.bos_format.is_none()
+            && self.eos_format.is_none()
+    }
+
+    // format returns `None` if no format option is given.
+    fn format(&self) -> Option<Format> {
+        if self.output_format.is_none()
+            && self.node_format.is_none()
+            && self.unk_format.is_none()
+            && self.bos_format.is_none()
+            && self.eos_format.is_none()
+            && self.dicrc.is_none()
+        {
+            return None;
+        }
+        let dicrc = self
+            .dicrc
+            .as_ref()
+            .map(|path| Dicrc::from_file(path).expect("failed to load dicrc"));
+        let mut format = match (&self.output_format, &dicrc) {
+            (Some(name), dicrc) => Format::named(name, dicrc.as_ref()),
+            (None, Some(dicrc)) if dicrc.get("node-format").is_some() => dicrc.format(""),
+            (None, _) => Ok(Format::lattice()),
+        }
+        .expect("failed to load output format");
+        let parse = |template: &String| template.parse::<Template>().expect("invalid format");
+        if let Some(node) = &self.node_format {
+            format.node = parse(node);
+            format.unk = format.node.clone();
+        }
+        if let Some(unk) = &self.unk_format {
+            format.unk = parse(unk);
+        }
+        if let Some(bos) = &self.bos_format {
+            format.bos = parse(bos);
+        }
+        if let Some(eos) = &self.eos_format {
+            format.eos = parse(eos);
+        }
+        Some(format)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum DictCommand {
+    /// Export a dictionary as MeCab source files (CSV, matrix.def, char.def and unk.def)
+    Export {
+        /// Output directory
+        #[arg(index = 1)]
+        out: PathBuf,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Encoding of output files
+        #[arg(short, long, value_enum, default_value = "utf8")]
+        encoding: Encoding,
+    },
+    /// Estimate missing context ids and costs of lexicon CSV entries from their features
+    Estimate {
+        /// Lexicon CSV whose left id, right id or cost may be empty
+        #[arg(index = 1)]
+        input: PathBuf,
+        /// Output CSV
+        #[arg(index = 2)]
+        out: PathBuf,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary, e.g. a trained one
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Encoding of the input and output files
+        #[arg(short, long, value_enum, default_value = "utf8")]
+        encoding: Encoding,
+        /// Number of leading features to compare
+        #[arg(long, default_value_t = 6)]
+        depth: usize,
+    },
+    /// Train word and connection costs from an annotated corpus and export them as MeCab source files
+    Train {
+        /// Annotated corpus in the MeCab output format
+        #[arg(index = 1)]
+        corpus: PathBuf,
+        /// Output directory
+        #[arg(index = 2)]
+        out: PathBuf,
+        /// Dictionary
+        #[arg(short, long, value_enum, default_value = "ipa")]
+        dict: Dict,
+        /// Custom dictionary
+        #[arg(short, long)]
+        custom_dict: Option<PathBuf>,
+        /// Encoding of the corpus and output files
+        #[arg(short, long, value_enum, default_value = "utf8")]
+        encoding: Encoding,
+        /// Number of passes over the corpus
+        #[arg(long, default_value_t = TrainConfig::default().epochs)]
+        epochs: usize,
+        /// Inverse strength of the L2 regularization
+        #[arg(long, default_value_t = TrainConfig::default().regularization)]
+        regularization: f64,
+        /// Scale between a weight and a cost
+        #[arg(long, default_value_t = TrainConfig::default().cost_factor)]
+        cost_factor: f64,
     },
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum LatticeFormat {
+    /// Graphviz format
+    Dot,
+    /// Standalone HTML page with inline SVG
+    Html,
+    /// JSON for external rescoring, which can be decoded by `kanpyo decode`
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum Output {
+    /// Surface and features separated by a tab with EOS per sentence, or a format given by the format options
+    Text,
+    /// Pretty-printed JSON array of tokens per input line
+    #[cfg(feature = "serde")]
+    Json,
+    /// JSON array of tokens per line
+    #[cfg(feature = "serde")]
+    Jsonl,
+    /// CoNLL-U sentence block per input line with UPOS tags
+    Conllu,
+    /// Space-separated tokens with escaped whitespace, which `kanpyo detokenize` restores, same as -Owakati
+    Wakati,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum Encoding {
+    /// EUC-JP
+    EucJp,
+    /// UTF-8
+    Utf8,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 enum Dict {
     Ipa,
@@ -83,12 +406,25 @@ impl KanpyoCommand {
         Tokenizer::new(dict)
     }
 
+    fn tokenizer_with_adjustments(
+        dict_type: Dict,
+        custom_dict: Option<PathBuf>,
+        adjustments: Option<PathBuf>,
+    ) -> Tokenizer {
+        let tokenizer = Self::tokenizer(dict_type, custom_dict);
+        match adjustments {
+            Some(path) => tokenizer.with_adjustments(
+                CostAdjustments::from_file(&path).expect("failed to load cost adjustments"),
+            ),
+            None => tokenizer,
+        }
+    }
+
     fn load_default_dict(_dict_type: Dict) -> dict::Dict {
         // Try embedded dictionary first (if compiled with embed-dict feature)
         #[cfg(feature = "mecab-ipadic")]
         {
-            let mut cursor = std::io::Cursor::new(EMBEDDED_DICT);
-            dict::Dict::load(&mut cursor).expect("failed to load embedded dict")
+            kanpyo::embedded::mecab_ipadic().expect("failed to load embedded dict")
         }
 
         // Fall back to file-based dictionary
@@ -103,12 +439,82 @@ impl KanpyoCommand {
         }
     }
 
-    fn tokenize(input: Option<String>, dict: Dict, custom_dict: Option<PathBuf>) {
-        let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
+    fn tokenize(
+        input: Option<String>,
+        tokenizer: Tokenizer,
+        output: Output,
+        filters: Filters,
+        format: Option<Format>,
+    ) {
+        let tagset = kanpyo::ud::Tagset::detect(&tokenizer.dict);
+        let splitter = SentenceSplitter::new();
+        let sentences = std::cell::Cell::new(0);
+        let print_sentence = |text: &str, tokens: Vec<kanpyo::token::Token>| match output {
+            Output::Wakati => println!("{}", kanpyo::wakati::wakati(&tokens)),
+            Output::Conllu => {
+                sentences.set(sentences.get() + 1);
+                kanpyo::ud::write_conllu(
+                    &mut std::io::stdout(),
+                    &tokenizer.dict,
+                    tagset,
+                    Some(&sentences.get().to_string()),
+                    text,
+                    &tokens,
+                )
+                .expect("failed to write")
+            }
+            #[cfg(feature = "serde")]
+            Output::Json => println!(
+                "{}",
+                kanpyo::token::json::to_json_pretty(&tokens, &tokenizer.dict)
+                    .expect("failed to serialize tokens")
+            ),
+            #[cfg(feature = "serde")]
+            Output::Jsonl => println!(
+                "{}",
+                kanpyo::token::json::to_json(&tokens, &tokenizer.dict)
+                    .expect("failed to serialize tokens")
+            ),
+            Output::Text => print_tokens(tokens, &tokenizer.dict),
+        };
+        let write_format = |format: &Format, text: &str| {
+            format
+                .write(
+                    &mut std::io::stdout(),
+                    &tokenizer.dict,
+                    text,
+                    &tokenizer.explain(text),
+                )
+                .expect("failed to write")
+        };
+        // only the text output prints EOS per sentence, and the others are per input line.
+        // a line without sentences like an empty one still prints EOS.
+        let print = |text: &str| match (&output, &format) {
+            // formats are written from the explanations, so sentences are split but not tokenized
+            (Output::Text, Some(format)) => {
+                let sentences = splitter.split(text);
+                if sentences.is_empty() {
+                    return write_format(format, text);
+                }
+                for sentence in sentences {
+                    write_format(format, sentence.text);
+                }
+            }
+            (Output::Text, None) => {
+                let sentences = tokenizer.tokenize_document(text, &splitter);
+                if sentences.is_empty() {
+                    return print_sentence(text, tokenizer.analyze(text, &filters));
+                }
+                for (sentence, tokens) in sentences {
+                    print_sentence(sentence.text, filters.apply(&tokenizer.dict, tokens));
+                }
+            }
+            _ => print_sentence(text, tokenizer.analyze(text, &filters)),
+        };
         loop {
             match &input {
                 Some(text) => {
-                    print_tokens(tokenizer.tokenize(text), &tokenizer.dict);
+                    print(text);
                     break;
                 }
                 None => {
@@ -119,32 +525,227 @@ impl KanpyoCommand {
                     if buf.is_empty() {
                         break;
                     }
-                    print_tokens(tokenizer.tokenize(buf.trim_end()), &tokenizer.dict);
+                    print(buf.trim_end_matches(['\n', '\r']));
                 }
             };
         }
     }
+    #[cfg(feature = "server")]
+    fn serve(
+        server: kanpyo::server::Server,
+        dict: Dict,
+        custom_dict: Option<PathBuf>,
+        adjustments: Option<PathBuf>,
+    ) {
+        let tokenizer = KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+        let handle = server.shutdown_handle();
+        ctrlc::set_handler(move || handle.shutdown()).expect("failed to set signal handler");
+        if let Some(addr) = server.local_addr() {
+            eprintln!("listening on http://{}", addr);
+        }
+        server.run(&tokenizer);
+    }
+    #[cfg(feature = "serde")]
+    fn rpc(dict: Dict, custom_dict: Option<PathBuf>, adjustments: Option<PathBuf>) {
+        let tokenizer = KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+        kanpyo::rpc::serve(
+            &tokenizer,
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+        )
+        .expect("failed to serve requests");
+    }
+    fn detokenize(input: Option<String>) {
+        let print = |line: &str| {
+            println!(
+                "{}",
+                kanpyo::wakati::detokenize_wakati(line).expect("failed to detokenize")
+            )
+        };
+        match input {
+            Some(line) => print(&line),
+            None => {
+                for line in std::io::stdin().lines() {
+                    print(&line.expect("failed to read from stdin"));
+                }
+            }
+        }
+    }
     fn graphviz(
         input: Option<String>,
         dict: Dict,
         custom_dict: Option<PathBuf>,
+        adjustments: Option<PathBuf>,
         dpi: usize,
         full_state: bool,
+        nbest: usize,
     ) {
-        let input = match input {
-            Some(text) => text,
-            None => {
-                let mut buf = String::new();
-                std::io::stdin()
-                    .read_line(&mut buf)
-                    .expect("failed to read from stdin");
-                buf.trim_end().to_string()
-            }
+        let input = read_input(input);
+
+        let tokenizer = KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+        let lattice = kanpyo::lattice::Lattice::build(&tokenizer.dict, &input);
+        kanpyo::graphviz::Graphviz { lattice }
+            .graphviz_with(
+                &mut std::io::stdout().lock(),
+                dpi,
+                full_state,
+                nbest,
+                tokenizer.model(),
+            )
+            .expect("failed to write graphviz");
+    }
+    fn lattice(
+        input: Option<String>,
+        dict: Dict,
+        custom_dict: Option<PathBuf>,
+        adjustments: Option<PathBuf>,
+        format: LatticeFormat,
+    ) {
+        let input = read_input(input);
+
+        let tokenizer = KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+        let lattice = kanpyo::lattice::Lattice::build(&tokenizer.dict, &input);
+        let mut stdout = std::io::stdout().lock();
+        match format {
+            LatticeFormat::Dot => kanpyo::graphviz::Graphviz { lattice }
+                .graphviz_with(&mut stdout, 48, false, 1, tokenizer.model())
+                .expect("failed to write graphviz"),
+            LatticeFormat::Html => kanpyo::html::Html { lattice }
+                .html_with(&mut stdout, &input, tokenizer.model())
+                .expect("failed to write html"),
+            #[cfg(feature = "serde")]
+            LatticeFormat::Json => writeln!(
+                stdout,
+                "{}",
+                lattice.to_json().expect("failed to serialize lattice")
+            )
+            .expect("failed to write json"),
+        }
+    }
+    #[cfg(feature = "serde")]
+    fn decode(input: Option<PathBuf>, dict: Dict, custom_dict: Option<PathBuf>) {
+        let json = match input {
+            Some(path) => std::fs::read_to_string(path).expect("failed to read lattice"),
+            None => std::io::read_to_string(std::io::stdin()).expect("failed to read from stdin"),
         };
 
         let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
-        let lattice = kanpyo::lattice::Lattice::build(&tokenizer.dict, &input);
-        kanpyo::graphviz::Graphviz { lattice }.graphviz(dpi, full_state);
+        let (lattice, model) = kanpyo::lattice::Lattice::from_json(&tokenizer.dict, &json)
+            .expect("failed to load lattice");
+        let tokens = lattice
+            .viterbi_with(&model)
+            .into_iter()
+            .map(kanpyo::token::Token::from)
+            .collect();
+        print_tokens(tokens, &tokenizer.dict);
+    }
+    fn explain(
+        input: Option<String>,
+        dict: Dict,
+        custom_dict: Option<PathBuf>,
+        adjustments: Option<PathBuf>,
+    ) {
+        let input = read_input(input);
+
+        let tokenizer = KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+        println!("surface\tword\tconnection\ttotal\talternative\textra\tfeatures");
+        for explanation in tokenizer.explain(&input) {
+            let (alternative, extra_cost) = match &explanation.alternative {
+                Some(alternative) => (
+                    node_surface(&alternative.node),
+                    format!("+{}", alternative.extra_cost),
+                ),
+                None => ("-", "-".to_string()),
+            };
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                node_surface(&explanation.node),
+                explanation.word_cost,
+                explanation.connection_cost,
+                explanation.total_cost,
+                alternative,
+                extra_cost,
+                explanation.node.features(&tokenizer.dict).join(",")
+            );
+        }
+    }
+    fn lookup(input: Option<String>, dict: Dict, custom_dict: Option<PathBuf>) {
+        let input = read_input(input);
+
+        let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
+        for lookup in tokenizer.dict.lookup(&input) {
+            let class = match lookup.class {
+                dict::EntryClass::Known => "KNOWN",
+                dict::EntryClass::Unknown => "UNKNOWN",
+            };
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                lookup.char_pos,
+                lookup.entry.surface,
+                class,
+                lookup.entry.morph.left_id,
+                lookup.entry.morph.right_id,
+                lookup.entry.morph.cost,
+                lookup.entry.features.join(",")
+            );
+        }
+    }
+    fn export(out: PathBuf, dict: Dict, custom_dict: Option<PathBuf>, encoding: Encoding) {
+        let encoding = match encoding {
+            Encoding::EucJp => encoding_rs::EUC_JP,
+            Encoding::Utf8 => encoding_rs::UTF_8,
+        };
+        let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
+        let config = kanpyo_dict::builder::config::Config::new(&out, encoding);
+        kanpyo_dict::export::export(&tokenizer.dict, &config).expect("failed to export dict");
+        println!("Exported dict to {}", out.display());
+    }
+    fn estimate(
+        input: PathBuf,
+        out: PathBuf,
+        dict: Dict,
+        custom_dict: Option<PathBuf>,
+        encoding: Encoding,
+        depth: usize,
+    ) {
+        let encoding = match encoding {
+            Encoding::EucJp => encoding_rs::EUC_JP,
+            Encoding::Utf8 => encoding_rs::UTF_8,
+        };
+        let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
+        Estimator::new(&tokenizer.dict, depth)
+            .estimate_file(&input, &out, encoding)
+            .expect("failed to estimate entries");
+        println!("Wrote estimated entries to {}", out.display());
+    }
+    fn train(
+        corpus: PathBuf,
+        out: PathBuf,
+        dict: Dict,
+        custom_dict: Option<PathBuf>,
+        encoding: Encoding,
+        config: TrainConfig,
+    ) {
+        let encoding = match encoding {
+            Encoding::EucJp => encoding_rs::EUC_JP,
+            Encoding::Utf8 => encoding_rs::UTF_8,
+        };
+        let tokenizer = KanpyoCommand::tokenizer(dict, custom_dict);
+        let corpus = Corpus::from_file(&corpus, encoding).expect("failed to load corpus");
+        let mut trainer = Trainer::new(&tokenizer.dict, &corpus, config);
+        if trainer.skipped() > 0 {
+            eprintln!(
+                "Skipped {} sentences not found in the lattice",
+                trainer.skipped()
+            );
+        }
+        for (epoch, objective) in trainer.train().into_iter().enumerate() {
+            eprintln!("epoch {}: objective {:.4}", epoch + 1, objective);
+        }
+        let config = kanpyo_dict::builder::config::Config::new(&out, encoding);
+        kanpyo_dict::export::export(&trainer.trained_dict(), &config)
+            .expect("failed to export dict");
+        println!("Exported trained dict to {}", out.display());
     }
     fn run(self) {
         match self.subcommand {
@@ -152,47 +753,190 @@ impl KanpyoCommand {
                 input,
                 dict,
                 custom_dict,
+                adjustments,
+                output,
+                filters,
+                normalize,
+                normalize_keep,
+                format,
             }) => {
-                KanpyoCommand::tokenize(input, dict, custom_dict);
+                let (output, format) = match format.is_wakati() {
+                    true => (Output::Wakati, None),
+                    false => (output, format.format()),
+                };
+                // wakati prints surfaces, so the text restored from it would be normalized
+                if normalize && matches!(output, Output::Wakati) {
+                    KanpyoCommand::command()
+                        .error(
+                            ErrorKind::ArgumentConflict,
+                            "--normalize cannot be used with --output wakati",
+                        )
+                        .exit();
+                }
+                let mut tokenizer =
+                    KanpyoCommand::tokenizer_with_adjustments(dict, custom_dict, adjustments);
+                if normalize {
+                    tokenizer = tokenizer.with_normalizer(
+                        Normalizer::new().with_keep(normalize_keep.unwrap_or_default().chars()),
+                    );
+                }
+                let filters = filters.into_iter().fold(Filters::new(), Filters::with);
+                KanpyoCommand::tokenize(input, tokenizer, output, filters, format);
             }
             Some(SubCommand::Graphviz {
                 input,
                 dict,
                 custom_dict,
+                adjustments,
                 dpi,
                 full_state,
+                nbest,
+            }) => {
+                KanpyoCommand::graphviz(
+                    input,
+                    dict,
+                    custom_dict,
+                    adjustments,
+                    dpi,
+                    full_state,
+                    nbest,
+                );
+            }
+            Some(SubCommand::Lattice {
+                input,
+                dict,
+                custom_dict,
+                adjustments,
+                format,
+            }) => {
+                KanpyoCommand::lattice(input, dict, custom_dict, adjustments, format);
+            }
+            #[cfg(feature = "serde")]
+            Some(SubCommand::Decode {
+                input,
+                dict,
+                custom_dict,
+            }) => {
+                KanpyoCommand::decode(input, dict, custom_dict);
+            }
+            #[cfg(feature = "server")]
+            Some(SubCommand::Serve {
+                port,
+                host,
+                workers,
+                max_nbest,
+                max_body_size,
+                dict,
+                custom_dict,
+                adjustments,
             }) => {
-                KanpyoCommand::graphviz(input, dict, custom_dict, dpi, full_state);
+                let server = kanpyo::server::Server::bind((host.as_str(), port), workers)
+                    .expect("failed to bind address")
+                    .with_max_nbest(max_nbest)
+                    .with_max_body_size(max_body_size);
+                KanpyoCommand::serve(server, dict, custom_dict, adjustments);
+            }
+            #[cfg(feature = "serde")]
+            Some(SubCommand::Rpc {
+                dict,
+                custom_dict,
+                adjustments,
+            }) => {
+                KanpyoCommand::rpc(dict, custom_dict, adjustments);
+            }
+            Some(SubCommand::Detokenize { input }) => {
+                KanpyoCommand::detokenize(input);
+            }
+            Some(SubCommand::Explain {
+                input,
+                dict,
+                custom_dict,
+                adjustments,
+            }) => {
+                KanpyoCommand::explain(input, dict, custom_dict, adjustments);
+            }
+            Some(SubCommand::Lookup {
+                input,
+                dict,
+                custom_dict,
+            }) => {
+                KanpyoCommand::lookup(input, dict, custom_dict);
+            }
+            Some(SubCommand::Dict {
+                subcommand:
+                    DictCommand::Export {
+                        out,
+                        dict,
+                        custom_dict,
+                        encoding,
+                    },
+            }) => {
+                KanpyoCommand::export(out, dict, custom_dict, encoding);
+            }
+            Some(SubCommand::Dict {
+                subcommand:
+                    DictCommand::Estimate {
+                        input,
+                        out,
+                        dict,
+                        custom_dict,
+                        encoding,
+                        depth,
+                    },
+            }) => {
+                KanpyoCommand::estimate(input, out, dict, custom_dict, encoding, depth);
+            }
+            Some(SubCommand::Dict {
+                subcommand:
+                    DictCommand::Train {
+                        corpus,
+                        out,
+                        dict,
+                        custom_dict,
+                        encoding,
+                        epochs,
+                        regularization,
+                        cost_factor,
+                    },
+            }) => {
+                let config = TrainConfig {
+                    epochs,
+                    regularization,
+                    cost_factor,
+                    ..Default::default()
+                };
+                KanpyoCommand::train(corpus, out, dict, custom_dict, encoding, config);
             }
             None => {
-                KanpyoCommand::tokenize(None, Dict::Ipa, None);
+                let tokenizer = KanpyoCommand::tokenizer(Dict::Ipa, None);
+                let filters = Filters::new();
+                KanpyoCommand::tokenize(None, tokenizer, Output::Text, filters, None);
             }
         }
     }
 }
 
+// read_input returns the input text, or the first line of stdin without it.
+fn read_input(input: Option<String>) -> String {
+    input.unwrap_or_else(|| {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_line(&mut buf)
+            .expect("failed to read from stdin");
+        buf.trim_end().to_string()
+    })
+}
+
+fn node_surface(node: &Node) -> &str {
+    match node {
+        Node::Dummy { .. } => "EOS",
+        Node::Known(word) | Node::Unknown(word) => &word.surface,
+    }
+}
+
 fn print_tokens(tokens: Vec<kanpyo::token::Token>, dict: &dict::Dict) {
     for token in tokens {
-        let morph_features = if token.id != BOS_EOS_ID {
-            match token.class {
-                kanpyo::token::TokenClass::Known => dict.morph_feature_table.morph_features
-                    [token.id as usize - 1]
-                    .iter()
-                    .map(|&idx| dict.morph_feature_table.name_list[idx as usize].clone())
-                    .collect::<Vec<_>>(),
-                kanpyo::token::TokenClass::Unknown => dict
-                    .unk_dict
-                    .morph_feature_table
-                    .morph_features[token.id as usize - 1]
-                    .iter()
-                    .map(|&idx| dict.unk_dict.morph_feature_table.name_list[idx as usize].clone())
-                    .collect::<Vec<_>>(),
-                kanpyo::token::TokenClass::Dummy => Vec::new(),
-            }
-        } else {
-            Vec::new()
-        };
-        println!("{}\t{}", token.surface, morph_features.join(","))
+        println!("{}\t{}", token.surface, token.features(dict).join(","))
     }
 }
 
@@ -0,0 +1,9 @@
+use kanpyo_dict::{dict::Dict, error::Result};
+
+/// MeCab IPA dictionary embedded by the `mecab-ipadic` feature.
+pub static MECAB_IPADIC: &[u8] = include_bytes!(env!("KANPYO_MECAB_IPADIC_PATH"));
+
+/// Loads the embedded MeCab IPA dictionary.
+pub fn mecab_ipadic() -> Result<Dict> {
+    Dict::load(&mut std::io::Cursor::new(MECAB_IPADIC))
+}
@@ -0,0 +1,211 @@
+use std::{str::FromStr, sync::OnceLock};
+
+use kanpyo_dict::{
+    dict::Dict,
+    error::{KanpyoError, Result},
+};
+
+use crate::{
+    token::{Token, TokenClass},
+    ud::Tagset,
+};
+
+/// Parses a comma-separated feature prefix like `名詞,固有名詞`.
+pub fn parse_pos_prefix(pattern: &str) -> Vec<String> {
+    pattern.split(',').map(str::to_string).collect()
+}
+
+/// Returns whether features start with a prefix parsed by `parse_pos_prefix`.
+pub fn matches_pos_prefix(features: &[&str], prefix: &[String]) -> bool {
+    features.len() >= prefix.len() && prefix.iter().zip(features).all(|(p, f)| p == f)
+}
+
+/// Default minimum length of katakana words stemmed by `TokenFilter::KatakanaStem`.
+pub const DEFAULT_KATAKANA_STEM_LENGTH: usize = 4;
+
+/// TokenFilter post-processes tokens. Filters keep the offsets into the input and
+/// change only surfaces, so a surface may differ from the text between the offsets.
+///
+/// A filter can be parsed from a spec used by `kanpyo tokenize --filter`.
+///
+/// ```text
+/// stop-pos=助詞          removes tokens whose features start with a comma-separated prefix
+/// base-form              replaces surfaces with base forms
+/// katakana-stem[=4]      strips a trailing ー from katakana words of at least 4 chars
+/// width                  normalizes full-width ASCII and half-width katakana
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenFilter {
+    /// Removes tokens whose features start with any of the prefixes.
+    StopPos(Vec<Vec<String>>),
+    /// Replaces surfaces with base forms if any.
+    BaseForm,
+    /// Strips a trailing ー from katakana words whose length is at least the minimum, e.g. サーバー to サーバ.
+    KatakanaStem(usize),
+    /// Maps full-width ASCII to half-width and half-width katakana to full-width.
+    Width,
+}
+
+impl TokenFilter {
+    pub fn stop_pos<I: IntoIterator<Item = S>, S: AsRef<str>>(prefixes: I) -> Self {
+        TokenFilter::StopPos(
+            prefixes
+                .into_iter()
+                .map(|prefix| parse_pos_prefix(prefix.as_ref()))
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for TokenFilter {
+    type Err = KanpyoError;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let invalid = || KanpyoError::Parse(format!("invalid filter: {}", spec));
+        let (name, arg) = match spec.split_once('=') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (spec, None),
+        };
+        match (name, arg) {
+            ("stop-pos", Some(prefix)) if !prefix.is_empty() => Ok(TokenFilter::stop_pos([prefix])),
+            ("base-form", None) => Ok(TokenFilter::BaseForm),
+            ("katakana-stem", None) => Ok(TokenFilter::KatakanaStem(DEFAULT_KATAKANA_STEM_LENGTH)),
+            ("katakana-stem", Some(length)) => length
+                .parse()
+                .map(TokenFilter::KatakanaStem)
+                .map_err(|_| invalid()),
+            ("width", None) => Ok(TokenFilter::Width),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Filters is a pipeline of filters applied in order to tokens of a dictionary.
+/// The tagset of the dictionary, which decides base forms, is detected on the first `apply`
+/// and reused, so filters are for tokens of one dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filters {
+    filters: Vec<TokenFilter>,
+    tagset: OnceLock<Tagset>,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, filter: TokenFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Applies the filters to tokens of the dictionary. BOS and EOS are kept as they are.
+    pub fn apply(&self, dict: &Dict, mut tokens: Vec<Token>) -> Vec<Token> {
+        for filter in &self.filters {
+            match filter {
+                TokenFilter::StopPos(prefixes) => tokens.retain(|token| {
+                    let features = token.features(dict);
+                    !prefixes
+                        .iter()
+                        .any(|prefix| matches_pos_prefix(&features, prefix))
+                }),
+                TokenFilter::BaseForm => {
+                    let tagset = self.tagset.get_or_init(|| Tagset::detect(dict));
+                    for token in &mut tokens {
+                        if let Some(lemma) = tagset.lemma(&token.features(dict)) {
+                            token.surface = lemma.to_string();
+                        }
+                    }
+                }
+                TokenFilter::KatakanaStem(min_length) => {
+                    for token in word_tokens(&mut tokens) {
+                        stem_katakana(&mut token.surface, *min_length);
+                    }
+                }
+                TokenFilter::Width => {
+                    for token in word_tokens(&mut tokens) {
+                        token.surface = normalize_width(&token.surface);
+                    }
+                }
+            }
+        }
+        tokens
+    }
+}
+
+fn word_tokens(tokens: &mut [Token]) -> impl Iterator<Item = &mut Token> {
+    tokens
+        .iter_mut()
+        .filter(|token| token.class != TokenClass::Dummy)
+}
+
+fn is_katakana(ch: char) -> bool {
+    matches!(ch, '\u{30A1}'..='\u{30FA}' | 'ー' | '\u{30FD}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}')
+}
+
+// stem_katakana strips a trailing ー like Lucene's JapaneseKatakanaStemFilter.
+fn stem_katakana(surface: &mut String, min_length: usize) {
+    if surface.ends_with('ー')
+        && surface.chars().count() >= min_length
+        && surface.chars().all(is_katakana)
+    {
+        surface.pop();
+    }
+}
+
+// HALF_WIDTH_KATAKANA maps U+FF65..=U+FF9F to full-width.
+const HALF_WIDTH_KATAKANA: [char; 59] = [
+    '・', 'ヲ', 'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ッ', 'ー', 'ア', 'イ', 'ウ', 'エ',
+    'オ', 'カ', 'キ', 'ク', 'ケ', 'コ', 'サ', 'シ', 'ス', 'セ', 'ソ', 'タ', 'チ', 'ツ', 'テ', 'ト',
+    'ナ', 'ニ', 'ヌ', 'ネ', 'ノ', 'ハ', 'ヒ', 'フ', 'ヘ', 'ホ', 'マ', 'ミ', 'ム', 'メ', 'モ', 'ヤ',
+    'ユ', 'ヨ', 'ラ', 'リ', 'ル', 'レ', 'ロ', 'ワ', 'ン', '\u{3099}', '\u{309A}',
+];
+
+// compose returns a katakana with a (semi-)voiced sound mark, e.g. カ and ゙ to ガ.
+fn compose(base: char, mark: char) -> Option<char> {
+    match (base, mark) {
+        ('ウ', '\u{3099}') => Some('ヴ'),
+        ('ワ', '\u{3099}') => Some('ヷ'),
+        ('ヲ', '\u{3099}') => Some('ヺ'),
+        (
+            'カ' | 'キ' | 'ク' | 'ケ' | 'コ' | 'サ' | 'シ' | 'ス' | 'セ' | 'ソ' | 'タ' | 'チ'
+            | 'ツ' | 'テ' | 'ト' | 'ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ',
+            '\u{3099}',
+        ) => char::from_u32(base as u32 + 1),
+        ('ハ' | 'ヒ' | 'フ' | 'ヘ' | 'ホ', '\u{309A}') => char::from_u32(base as u32 + 2),
+        _ => None,
+    }
+}
+
+/// Maps full-width ASCII to half-width and half-width katakana to full-width
+/// like Lucene's CJKWidthFilter. A half-width sound mark is composed with the preceding katakana.
+pub fn normalize_width(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\u{FF01}'..='\u{FF5E}' => {
+                normalized.push(char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch))
+            }
+            '\u{FF65}'..='\u{FF9F}' => {
+                let ch = HALF_WIDTH_KATAKANA[(ch as u32 - 0xFF65) as usize];
+                let composed = normalized
+                    .chars()
+                    .next_back()
+                    .and_then(|base| compose(base, ch));
+                match composed {
+                    Some(composed) => {
+                        normalized.pop();
+                        normalized.push(composed);
+                    }
+                    None => normalized.push(ch),
+                }
+            }
+            _ => normalized.push(ch),
+        }
+    }
+    normalized
+}
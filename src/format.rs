@@ -0,0 +1,422 @@
+use std::{collections::HashMap, io::Write, path::Path, str::FromStr};
+
+use kanpyo_dict::{
+    dict::Dict,
+    error::{KanpyoError, Result},
+};
+
+use crate::lattice::{Explanation, node::Node};
+
+// IPADIC_DICRC is the output formats of the dicrc of IPADIC.
+const IPADIC_DICRC: &str = r"
+; yomi
+node-format-yomi = %pS%f[7]
+unk-format-yomi = %M
+eos-format-yomi  = \n
+
+; simple
+node-format-simple = %m\t%F-[0,1,2,3]\n
+eos-format-simple = EOS\n
+
+; ChaSen
+node-format-chasen = %m\t%f[7]\t%f[6]\t%F-[0,1,2,3]\t%f[4]\t%f[5]\n
+unk-format-chasen  = %m\t%m\t%m\t%F-[0,1,2,3]\t\t\n
+eos-format-chasen  = EOS\n
+
+; ChaSen (include spaces)
+node-format-chasen2 = %M\t%f[7]\t%f[6]\t%F-[0,1,2,3]\t%f[4]\t%f[5]\n
+unk-format-chasen2  = %M\t%m\t%m\t%F-[0,1,2,3]\t\t\n
+eos-format-chasen2  = EOS\n
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Directive {
+    Text(String),
+    // %m and %M. Kanpyo doesn't skip white spaces, so they are the same.
+    Surface,
+    // %pS, the white spaces between the previous node and the node
+    Whitespace,
+    // %H
+    Features,
+    // %f[N,M] joined by ','
+    Feature(Vec<usize>),
+    // %FC[N,M] joined by C skipping `*`
+    JoinedFeature(Vec<usize>, String),
+    // %s
+    Stat,
+    // %S
+    Sentence,
+    // %L
+    SentenceLength,
+    // %c and %pw
+    WordCost,
+    // %ps
+    Start,
+    // %pe
+    End,
+    // %pl and %pL
+    Length,
+    // %pC
+    ConnectionCost,
+    // %pc
+    TotalCost,
+    // %pn
+    Cost,
+    // %pb
+    Best,
+    // %phl
+    LeftId,
+    // %phr
+    RightId,
+}
+
+/// Template is a format string of MeCab like `%m\t%f[0]\n`.
+///
+/// Supported directives are `%m`, `%M`, `%H`, `%f[N,...]`, `%FC[N,...]`, `%s`, `%S`, `%L`, `%c`,
+/// `%ps`, `%pe`, `%pS`, `%pl`, `%pL`, `%pw`, `%pC`, `%pc`, `%pn`, `%pb`, `%phl`, `%phr` and `%%`,
+/// and escapes are `\t`, `\n`, `\r`, `\s` and `\\`. Out of range features are written as `*` by `%f`,
+/// and `%F` skips them and `*` like MeCab, e.g. `%F-[0,1,2,3]` of `名詞,一般,*,*` is `名詞-一般`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Template(Vec<Directive>);
+
+fn parse_indices<I: Iterator<Item = char>>(chars: &mut I, format: &str) -> Result<Vec<usize>> {
+    let invalid = || KanpyoError::InvalidFormat(format!("invalid feature index in {:?}", format));
+    if chars.next() != Some('[') {
+        return Err(invalid());
+    }
+    let mut indices = String::new();
+    for ch in chars.by_ref() {
+        if ch == ']' {
+            return indices
+                .split(',')
+                .map(|i| i.trim().parse().map_err(|_| invalid()))
+                .collect();
+        }
+        indices.push(ch);
+    }
+    Err(invalid())
+}
+
+impl FromStr for Template {
+    type Err = KanpyoError;
+
+    fn from_str(format: &str) -> Result<Self> {
+        let unsupported = |directive: &str| {
+            KanpyoError::InvalidFormat(format!(
+                "unsupported directive {} in {:?}",
+                directive, format
+            ))
+        };
+        let mut directives = vec![];
+        let mut text = String::new();
+        let mut chars = format.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('s') => text.push(' '),
+                    Some('\\') | None => text.push('\\'),
+                    Some(ch) => {
+                        text.push('\\');
+                        text.push(ch);
+                    }
+                },
+                '%' => {
+                    let directive = match (chars.next(), chars.clone().next()) {
+                        (Some('%'), _) => {
+                            text.push('%');
+                            continue;
+                        }
+                        (Some('m' | 'M'), _) => Directive::Surface,
+                        (Some('H'), _) => Directive::Features,
+                        (Some('f'), _) => Directive::Feature(parse_indices(&mut chars, format)?),
+                        (Some('F'), Some(separator)) => {
+                            chars.next();
+                            let indices = parse_indices(&mut chars, format)?;
+                            Directive::JoinedFeature(indices, separator.to_string())
+                        }
+                        (Some('s'), _) => Directive::Stat,
+                        (Some('S'), _) => Directive::Sentence,
+                        (Some('L'), _) => Directive::SentenceLength,
+                        (Some('c'), _) => Directive::WordCost,
+                        (Some('p'), _) => match chars.next() {
+                            Some('S') => Directive::Whitespace,
+                            Some('s') => Directive::Start,
+                            Some('e') => Directive::End,
+                            Some('l' | 'L') => Directive::Length,
+                            Some('w') => Directive::WordCost,
+                            Some('C') => Directive::ConnectionCost,
+                            Some('c') => Directive::TotalCost,
+                            Some('n') => Directive::Cost,
+                            Some('b') => Directive::Best,
+                            Some('h') => match chars.next() {
+                                Some('l') => Directive::LeftId,
+                                Some('r') => Directive::RightId,
+                                _ => return Err(unsupported("%ph")),
+                            },
+                            _ => return Err(unsupported("%p")),
+                        },
+                        (ch, _) => {
+                            return Err(unsupported(&format!(
+                                "%{}",
+                                ch.map(String::from).unwrap_or_default()
+                            )));
+                        }
+                    };
+                    if !text.is_empty() {
+                        directives.push(Directive::Text(std::mem::take(&mut text)));
+                    }
+                    directives.push(directive);
+                }
+                ch => text.push(ch),
+            }
+        }
+        if !text.is_empty() {
+            directives.push(Directive::Text(text));
+        }
+        Ok(Template(directives))
+    }
+}
+
+// Context is what a template is rendered with.
+struct Context<'a> {
+    dict: &'a Dict,
+    bos: bool,
+    input: &'a str,
+    node: &'a Node,
+    // byte position where the previous node ends
+    previous_end: usize,
+    word_cost: i32,
+    connection_cost: i32,
+    total_cost: i32,
+}
+
+impl Template {
+    fn render(&self, out: &mut String, ctx: &Context) {
+        let surface = match ctx.node {
+            Node::Dummy { .. } => "",
+            Node::Known(word) | Node::Unknown(word) => word.surface.as_str(),
+        };
+        let features = ctx.node.features(ctx.dict);
+        for directive in &self.0 {
+            match directive {
+                Directive::Text(text) => out.push_str(text),
+                Directive::Surface => out.push_str(surface),
+                Directive::Features => out.push_str(&features.join(",")),
+                Directive::Whitespace => out.push_str(
+                    ctx.input
+                        .get(ctx.previous_end..ctx.node.byte_pos())
+                        .unwrap_or_default(),
+                ),
+                Directive::Feature(indices) => {
+                    let values = indices
+                        .iter()
+                        .map(|&i| features.get(i).copied().unwrap_or("*"))
+                        .collect::<Vec<_>>();
+                    out.push_str(&values.join(","));
+                }
+                Directive::JoinedFeature(indices, separator) => {
+                    let values = indices
+                        .iter()
+                        .filter_map(|&i| features.get(i).copied())
+                        .filter(|&value| value != "*")
+                        .collect::<Vec<_>>();
+                    out.push_str(&values.join(separator));
+                }
+                Directive::Stat => out.push(match ctx.node {
+                    Node::Known(_) => '0',
+                    Node::Unknown(_) => '1',
+                    Node::Dummy { .. } if ctx.bos => '2',
+                    Node::Dummy { .. } => '3',
+                }),
+                Directive::Sentence => out.push_str(ctx.input),
+                Directive::SentenceLength => out.push_str(&ctx.input.len().to_string()),
+                Directive::WordCost => out.push_str(&ctx.word_cost.to_string()),
+                Directive::Start => out.push_str(&ctx.node.byte_pos().to_string()),
+                Directive::End => out.push_str(&(ctx.node.byte_pos() + surface.len()).to_string()),
+                Directive::Length => out.push_str(&surface.len().to_string()),
+                Directive::ConnectionCost => out.push_str(&ctx.connection_cost.to_string()),
+                Directive::TotalCost => out.push_str(&ctx.total_cost.to_string()),
+                Directive::Cost => out.push_str(&(ctx.word_cost + ctx.connection_cost).to_string()),
+                // only the best path is written
+                Directive::Best => out.push('*'),
+                Directive::LeftId => out.push_str(&ctx.node.morph().left_id.to_string()),
+                Directive::RightId => out.push_str(&ctx.node.morph().right_id.to_string()),
+            }
+        }
+    }
+}
+
+/// Format is a set of templates for BOS, known words, unknown words and EOS like
+/// `-B`, `-F`, `-U` and `-E` of MeCab.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    pub bos: Template,
+    pub node: Template,
+    pub unk: Template,
+    pub eos: Template,
+}
+
+impl Format {
+    /// Returns a format whose unknown words are written like known words.
+    pub fn new(node: &str) -> Result<Self> {
+        let node = node.parse::<Template>()?;
+        Ok(Format {
+            bos: Template::default(),
+            unk: node.clone(),
+            node,
+            eos: "EOS\\n".parse()?,
+        })
+    }
+
+    /// The default output of Kanpyo and MeCab.
+    pub fn lattice() -> Self {
+        Self::new("%m\\t%H\\n").expect("valid format")
+    }
+
+    /// Words separated by spaces.
+    pub fn wakati() -> Self {
+        Format {
+            eos: "\\n".parse().expect("valid format"),
+            ..Self::new("%m ").expect("valid format")
+        }
+    }
+
+    /// Returns a built-in format or one defined in `dicrc`.
+    /// `lattice` and `wakati` are built in, and the formats of IPADIC are used without `dicrc`.
+    pub fn named(name: &str, dicrc: Option<&Dicrc>) -> Result<Self> {
+        match name {
+            "lattice" => Ok(Self::lattice()),
+            "wakati" => Ok(Self::wakati()),
+            _ => match dicrc {
+                Some(dicrc) => dicrc.format(name),
+                None => Dicrc::ipadic().format(name),
+            },
+        }
+    }
+
+    /// Writes the best path of a sentence from `Lattice::explain`, which ends with EOS.
+    pub fn write<W: Write>(
+        &self,
+        w: &mut W,
+        dict: &Dict,
+        input: &str,
+        explanations: &[Explanation],
+    ) -> std::io::Result<()> {
+        let mut out = String::new();
+        let bos = Node::Dummy {
+            byte_pos: 0,
+            char_pos: 0,
+            morph: kanpyo_dict::morph::Morph::new(0, 0, 0),
+        };
+        self.bos.render(
+            &mut out,
+            &Context {
+                dict,
+                bos: true,
+                input,
+                node: &bos,
+                previous_end: 0,
+                word_cost: 0,
+                connection_cost: 0,
+                total_cost: 0,
+            },
+        );
+        let mut previous_end = 0;
+        for explanation in explanations {
+            let template = match explanation.node {
+                Node::Known(_) => &self.node,
+                Node::Unknown(_) => &self.unk,
+                Node::Dummy { .. } => &self.eos,
+            };
+            template.render(
+                &mut out,
+                &Context {
+                    dict,
+                    bos: false,
+                    input,
+                    node: &explanation.node,
+                    previous_end,
+                    word_cost: explanation.word_cost,
+                    connection_cost: explanation.connection_cost,
+                    total_cost: explanation.total_cost,
+                },
+            );
+            previous_end = match &explanation.node {
+                Node::Known(word) | Node::Unknown(word) => {
+                    explanation.node.byte_pos() + word.surface.len()
+                }
+                Node::Dummy { .. } => previous_end,
+            };
+        }
+        w.write_all(out.as_bytes())
+    }
+}
+
+/// Dicrc is the output formats defined in a dicrc file of a MeCab dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dicrc(HashMap<String, String>);
+
+impl Dicrc {
+    /// Parses `key = value` lines. Lines starting with `;` or `#` are comments.
+    pub fn parse(text: &str) -> Self {
+        Dicrc(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with(';') && !line.starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+        )
+    }
+
+    /// Reads a dicrc encoded in UTF-8 or EUC-JP like that of IPADIC.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let text = match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => {
+                let (text, _, had_errors) = encoding_rs::EUC_JP.decode(e.as_bytes());
+                if had_errors {
+                    return Err(KanpyoError::EncodingError);
+                }
+                text.into_owned()
+            }
+        };
+        Ok(Self::parse(&text))
+    }
+
+    /// The output formats of IPADIC: `yomi`, `simple`, `chasen` and `chasen2`.
+    pub fn ipadic() -> Self {
+        Self::parse(IPADIC_DICRC)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Returns the format of `node-format-NAME`, `unk-format-NAME`, `bos-format-NAME` and
+    /// `eos-format-NAME`. The empty name refers to `node-format` and so on.
+    pub fn format(&self, name: &str) -> Result<Format> {
+        let key = |kind: &str| match name {
+            "" => format!("{}-format", kind),
+            _ => format!("{}-format-{}", kind, name),
+        };
+        let node = self
+            .get(&key("node"))
+            .ok_or_else(|| KanpyoError::InvalidFormat(format!("{} is not defined", key("node"))))?;
+        let mut format = Format::new(node)?;
+        if let Some(unk) = self.get(&key("unk")) {
+            format.unk = unk.parse()?;
+        }
+        if let Some(bos) = self.get(&key("bos")) {
+            format.bos = bos.parse()?;
+        }
+        if let Some(eos) = self.get(&key("eos")) {
+            format.eos = eos.parse()?;
+        }
+        Ok(format)
+    }
+}
@@ -1,6 +1,16 @@
-use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::Write,
+};
 
-use crate::lattice::{Lattice, node::Node};
+use crate::lattice::{
+    Lattice,
+    cost::{CostModel, DictCostModel},
+    node::Node,
+};
+
+const BEST_COLOR: &str = "blue";
+const NBEST_COLORS: [&str; 5] = ["#e6550d", "#31a354", "#756bb1", "#d6616b", "#8c6d31"];
 
 pub struct Graphviz<'a> {
     pub lattice: Lattice<'a>,
@@ -27,26 +37,98 @@ impl Graphviz<'_> {
         visited.into_iter().collect()
     }
 
-    pub fn graphviz(&self, dpi: usize, full_state: bool) {
-        let bests = self
+    // paths returns the best path and up to `nbest - 1` alternatives, each starting from BOS.
+    fn paths<C: CostModel + ?Sized>(&self, nbest: usize, model: &C) -> Vec<Vec<Node>> {
+        let bos = self.lattice.nodes.first().expect("BOS not found");
+        let best = self.lattice.viterbi_with(model);
+        let alternatives = self
             .lattice
-            .viterbi()
+            .nbest_with(nbest, model)
             .into_iter()
-            .collect::<BTreeSet<Node>>();
-        println!("graph lattice {{");
-        println!("dpi={dpi};");
-        println!("graph [style=filled, splines=true, overlap=false, fontsize=30, rankdir=LR]");
-        println!("edge [fontname=Helvetica, fontcolor=red, color=\"#606060\"]");
-        println!("node [shape=box, style=filled, fillcolor=\"#e8e8f0\", fontname=Helvetica]");
+            .map(|(path, _)| path)
+            .filter(|path| *path != best)
+            .take(nbest.saturating_sub(1))
+            .collect::<Vec<_>>();
+        std::iter::once(best)
+            .chain(alternatives)
+            .map(|path| std::iter::once(bos.clone()).chain(path).collect())
+            .collect()
+    }
+
+    /// Writes the lattice in Graphviz format.
+    /// Nodes are annotated with word costs and edges with connection costs.
+    /// The best path is drawn in blue and the other `nbest - 1` best paths in `NBEST_COLORS`.
+    pub fn graphviz<W: Write>(
+        &self,
+        w: &mut W,
+        dpi: usize,
+        full_state: bool,
+        nbest: usize,
+    ) -> std::io::Result<()> {
+        self.graphviz_with(w, dpi, full_state, nbest, &DictCostModel)
+    }
+
+    /// Writes the lattice in Graphviz format with the costs and best paths under a cost model.
+    pub fn graphviz_with<W: Write, C: CostModel + ?Sized>(
+        &self,
+        w: &mut W,
+        dpi: usize,
+        full_state: bool,
+        nbest: usize,
+        model: &C,
+    ) -> std::io::Result<()> {
+        let paths = self.paths(nbest.max(1), model);
+        // index of each node in the lattice for the costs of the model
+        let indices = self
+            .lattice
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node, i))
+            .collect::<BTreeMap<_, _>>();
+        // rank of the best path which a node or an edge belongs to
+        let mut node_ranks = BTreeMap::new();
+        let mut edge_ranks = BTreeMap::new();
+        for (rank, path) in paths.iter().enumerate() {
+            for node in path {
+                node_ranks.entry(node).or_insert(rank);
+            }
+            for pair in path.windows(2) {
+                edge_ranks.entry((&pair[0], &pair[1])).or_insert(rank);
+            }
+        }
+        let color_of = |rank: usize| {
+            if rank == 0 {
+                BEST_COLOR
+            } else {
+                NBEST_COLORS[(rank - 1) % NBEST_COLORS.len()]
+            }
+        };
+
+        writeln!(w, "graph lattice {{")?;
+        writeln!(w, "dpi={dpi};")?;
+        writeln!(
+            w,
+            "graph [style=filled, splines=true, overlap=false, fontsize=30, rankdir=LR]"
+        )?;
+        writeln!(
+            w,
+            "edge [fontname=Helvetica, fontcolor=red, color=\"#606060\"]"
+        )?;
+        writeln!(
+            w,
+            "node [shape=box, style=filled, fillcolor=\"#e8e8f0\", fontname=Helvetica]"
+        )?;
 
         let visible_nodes = if !full_state {
+            let shown = node_ranks.keys().map(|&node| node.clone()).collect();
             self.bfs(
                 self.lattice
                     .nodes
                     .last()
                     .expect("last node not found")
                     .clone(),
-                &bests,
+                &shown,
             )
         } else {
             self.lattice.nodes.clone()
@@ -54,38 +136,16 @@ impl Graphviz<'_> {
 
         for (visible_id, visible_node) in visible_nodes.iter().enumerate() {
             let label = match visible_node {
-                Node::Known(node) => format!(
-                    "{}\n{}\n{}",
-                    node.surface,
-                    self.lattice.dict.morph_feature_table.morph_features[node.id as usize - 1]
-                        .iter()
-                        .map(
-                            |&idx| self.lattice.dict.morph_feature_table.name_list[idx as usize]
-                                .clone()
-                        )
-                        .filter(|s| s != "*")
-                        .collect::<Vec<String>>()
-                        .join("/"),
-                    node.morph.cost
-                ),
-                Node::Unknown(node) => format!(
+                Node::Known(node) | Node::Unknown(node) => format!(
                     "{}\n{}\n{}",
                     node.surface,
-                    self.lattice
-                        .dict
-                        .unk_dict
-                        .morph_feature_table
-                        .morph_features[node.id as usize - 1]
-                        .iter()
-                        .map(
-                            |&idx| self.lattice.dict.unk_dict.morph_feature_table.name_list
-                                [idx as usize]
-                                .clone()
-                        )
-                        .filter(|s| s != "*")
-                        .collect::<Vec<String>>()
+                    visible_node
+                        .features(self.lattice.dict)
+                        .into_iter()
+                        .filter(|&s| s != "*")
+                        .collect::<Vec<_>>()
                         .join("/"),
-                    node.morph.cost
+                    model.node_cost_at(&self.lattice, indices[visible_node])
                 ),
                 _ => {
                     if visible_id == 0 {
@@ -100,21 +160,40 @@ impl Graphviz<'_> {
                 Node::Unknown(_) => "red",
                 Node::Dummy { .. } => "blue",
             };
-            if bests.contains(visible_node) || matches!(visible_node, Node::Dummy { .. }) {
-                println!(
-                    "{} [label=\"{}\", shape=ellipse, color={}, peripheries=2]",
-                    visible_id, label, color
-                );
-            } else {
-                let shape = match visible_node {
-                    Node::Known(_) => "box",
-                    Node::Unknown(_) => "diamond",
-                    Node::Dummy { .. } => "ellipse",
-                };
-                println!(
-                    "{} [label=\"{}\", shape={}, color={}]",
-                    visible_id, label, shape, color
-                );
+            match node_ranks.get(visible_node) {
+                Some(&rank) if rank > 0 && !matches!(visible_node, Node::Dummy { .. }) => {
+                    let shape = match visible_node {
+                        Node::Unknown(_) => "diamond",
+                        _ => "box",
+                    };
+                    writeln!(
+                        w,
+                        "{} [label=\"{}\", shape={}, color=\"{}\", peripheries=2]",
+                        visible_id,
+                        label,
+                        shape,
+                        color_of(rank)
+                    )?;
+                }
+                Some(_) => {
+                    writeln!(
+                        w,
+                        "{} [label=\"{}\", shape=ellipse, color={}, peripheries=2]",
+                        visible_id, label, color
+                    )?;
+                }
+                None => {
+                    let shape = match visible_node {
+                        Node::Known(_) => "box",
+                        Node::Unknown(_) => "diamond",
+                        Node::Dummy { .. } => "ellipse",
+                    };
+                    writeln!(
+                        w,
+                        "{} [label=\"{}\", shape={}, color={}]",
+                        visible_id, label, shape, color
+                    )?;
+                }
             }
         }
         let node_to_visible_id = visible_nodes
@@ -123,42 +202,39 @@ impl Graphviz<'_> {
             .map(|(id, node)| (node, id))
             .collect::<BTreeMap<_, _>>();
         for edge in self.lattice.edges.iter() {
-            for (id, node) in edge.iter().filter_map(|i| {
+            for (i, id, node) in edge.iter().filter_map(|&i| {
                 node_to_visible_id
-                    .get(&self.lattice.nodes[*i])
-                    .map(|&id| (id, &self.lattice.nodes[*i]))
+                    .get(&self.lattice.nodes[i])
+                    .map(|&id| (i, id, &self.lattice.nodes[i]))
             }) {
-                for (from_id, from_node) in
-                    self.lattice.edges[node.char_pos()].iter().filter_map(|&i| {
+                for (j, from_id, from_node) in
+                    self.lattice.edges[node.char_pos()].iter().filter_map(|&j| {
                         node_to_visible_id
-                            .get(&self.lattice.nodes[i])
-                            .map(|&id| (id, &self.lattice.nodes[i]))
+                            .get(&self.lattice.nodes[j])
+                            .map(|&id| (j, id, &self.lattice.nodes[j]))
                     })
                 {
                     if from_id == id {
                         continue;
                     }
 
-                    let label = format!(
-                        "{}",
-                        self.lattice.dict.connection_table.get(
-                            from_node.morph().right_id as usize,
-                            node.morph().left_id as usize
-                        )
-                    );
-                    let ok1 = bests.contains(from_node) || matches!(from_node, Node::Dummy { .. });
-                    let ok2 = bests.contains(node) || matches!(node, Node::Dummy { .. });
-                    if ok1 && ok2 {
-                        println!(
-                            "{} -- {} [label=\"{}\", style=bold, color=blue, fontcolor=blue]",
-                            from_id, id, label
-                        );
-                    } else {
-                        println!("{} -- {} [label=\"{}\"]", from_id, id, label);
+                    let label = format!("{}", model.edge_cost_at(&self.lattice, j, i));
+                    match edge_ranks.get(&(from_node, node)) {
+                        Some(&rank) => {
+                            let color = color_of(rank);
+                            writeln!(
+                                w,
+                                "{} -- {} [label=\"{}\", style=bold, color=\"{}\", fontcolor=\"{}\"]",
+                                from_id, id, label, color, color
+                            )?;
+                        }
+                        None => {
+                            writeln!(w, "{} -- {} [label=\"{}\"]", from_id, id, label)?;
+                        }
                     }
                 }
             }
         }
-        println!("}}");
+        writeln!(w, "}}")
     }
 }
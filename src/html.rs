@@ -0,0 +1,214 @@
+use std::{collections::BTreeSet, io::Write};
+
+use crate::lattice::{
+    INF, Lattice,
+    cost::{CostModel, DictCostModel},
+    node::Node,
+};
+
+const CELL_WIDTH: usize = 96;
+const NODE_HEIGHT: usize = 44;
+const ROW_HEIGHT: usize = 64;
+const MARGIN: usize = 16;
+
+const STYLE: &str = r#"
+body { font-family: Helvetica, Arial, sans-serif; margin: 16px; }
+.node rect { fill: #e8e8f0; stroke: #606060; stroke-width: 1; }
+.node.unknown rect { stroke: red; stroke-dasharray: 4 2; }
+.node.dummy rect { fill: #d0d8f0; }
+.node.best rect { stroke: blue; stroke-width: 3; }
+.node:hover rect { fill: #fff3b0; }
+.node text { font-size: 14px; text-anchor: middle; dominant-baseline: middle; pointer-events: none; }
+.node text.cost { font-size: 10px; fill: #606060; }
+.edge { stroke: #c0c0c0; stroke-width: 1; fill: none; }
+.edge.best { stroke: blue; stroke-width: 3; }
+.edge:hover { stroke: red; stroke-width: 3; }
+"#;
+
+pub struct Html<'a> {
+    pub lattice: Lattice<'a>,
+}
+
+// escape escapes a text for HTML and XML.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+impl Html<'_> {
+    fn char_len(node: &Node) -> usize {
+        match node {
+            Node::Dummy { .. } => 1,
+            Node::Known(word) | Node::Unknown(word) => word.surface.chars().count(),
+        }
+    }
+
+    // column returns the first column of a node. BOS takes the first column and EOS the last one.
+    fn column(&self, i: usize) -> usize {
+        match &self.lattice.nodes[i] {
+            Node::Dummy { char_pos, .. } if i != 0 => char_pos + 1,
+            Node::Dummy { .. } => 0,
+            node => node.char_pos() + 1,
+        }
+    }
+
+    // rows assigns each node a row so that nodes in the same row don't overlap.
+    // Nodes on the best path are placed in the first row.
+    fn rows(&self, bests: &BTreeSet<usize>) -> Vec<usize> {
+        let mut order = (0..self.lattice.nodes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| {
+            (
+                !bests.contains(&i),
+                self.column(i),
+                Self::char_len(&self.lattice.nodes[i]),
+            )
+        });
+        let mut rows = vec![0; self.lattice.nodes.len()];
+        // end column of the last node in each row
+        let mut row_ends: Vec<usize> = vec![];
+        for i in order {
+            let start = self.column(i);
+            let end = start + Self::char_len(&self.lattice.nodes[i]);
+            match row_ends.iter().position(|&row_end| row_end <= start) {
+                Some(row) => {
+                    rows[i] = row;
+                    row_ends[row] = end;
+                }
+                None => {
+                    rows[i] = row_ends.len();
+                    row_ends.push(end);
+                }
+            }
+        }
+        rows
+    }
+
+    /// Writes the lattice as a standalone HTML page with inline SVG.
+    /// Hovering a node shows its features and costs, and the best path is highlighted.
+    pub fn html<W: Write>(&self, w: &mut W, input: &str) -> std::io::Result<()> {
+        self.html_with(w, input, &DictCostModel)
+    }
+
+    /// Writes the lattice as a standalone HTML page with the costs and best path under a cost model.
+    pub fn html_with<W: Write, C: CostModel + ?Sized>(
+        &self,
+        w: &mut W,
+        input: &str,
+        model: &C,
+    ) -> std::io::Result<()> {
+        let nodes = &self.lattice.nodes;
+        let eos = nodes.len() - 1;
+        let (costs, pre_nodes) = self.lattice.forward(model);
+        let best_path = std::iter::once(0)
+            .chain(self.lattice.best_path(&pre_nodes))
+            .collect::<Vec<_>>();
+        let bests = best_path.iter().copied().collect::<BTreeSet<_>>();
+        let best_edges = best_path
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<BTreeSet<_>>();
+        let rows = self.rows(&bests);
+
+        let x = |i: usize| MARGIN + self.column(i) * CELL_WIDTH;
+        let width = |i: usize| Self::char_len(&nodes[i]) * CELL_WIDTH - MARGIN;
+        let y = |i: usize| MARGIN + rows[i] * ROW_HEIGHT;
+        let svg_width = MARGIN * 2 + (self.column(eos) + 1) * CELL_WIDTH;
+        let svg_height = MARGIN * 2 + (rows.iter().max().unwrap_or(&0) + 1) * ROW_HEIGHT;
+
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html lang=\"ja\">")?;
+        writeln!(w, "<head>")?;
+        writeln!(w, "<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>Lattice: {}</title>", escape(input))?;
+        writeln!(w, "<style>{STYLE}</style>")?;
+        writeln!(w, "</head>")?;
+        writeln!(w, "<body>")?;
+        writeln!(w, "<h1>{}</h1>", escape(input))?;
+        writeln!(
+            w,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{svg_width}\" height=\"{svg_height}\">"
+        )?;
+
+        // edges are drawn first so that nodes are on top of them
+        for (target_idx, target) in nodes.iter().enumerate().skip(1) {
+            let previous = match target {
+                Node::Dummy { .. } if target_idx != eos => continue,
+                _ => &self.lattice.edges[target.char_pos()],
+            };
+            for &previous_idx in previous {
+                let cost = model.edge_cost_at(&self.lattice, previous_idx, target_idx);
+                let class = if best_edges.contains(&(previous_idx, target_idx)) {
+                    "edge best"
+                } else {
+                    "edge"
+                };
+                let (x1, y1) = (x(previous_idx) + width(previous_idx), y(previous_idx));
+                let (x2, y2) = (x(target_idx), y(target_idx));
+                let (y1, y2) = (y1 + NODE_HEIGHT / 2, y2 + NODE_HEIGHT / 2);
+                writeln!(
+                    w,
+                    "<path class=\"{class}\" d=\"M{x1},{y1} C{},{y1} {},{y2} {x2},{y2}\"><title>connection cost: {cost}</title></path>",
+                    x1 + MARGIN,
+                    x2 - MARGIN,
+                )?;
+            }
+        }
+
+        for (i, node) in nodes.iter().enumerate() {
+            let (class, surface) = match node {
+                Node::Dummy { .. } => ("dummy", if i == 0 { "BOS" } else { "EOS" }),
+                Node::Known(word) => ("known", word.surface.as_str()),
+                Node::Unknown(word) => ("unknown", word.surface.as_str()),
+            };
+            let best = if bests.contains(&i) { " best" } else { "" };
+            let morph = node.morph();
+            let word_cost = model.node_cost_at(&self.lattice, i);
+            let mut title = format!(
+                "{}\n{}\nword cost: {}\nleft id: {}\nright id: {}",
+                surface,
+                node.features(self.lattice.dict).join(","),
+                word_cost,
+                morph.left_id,
+                morph.right_id,
+            );
+            if let Some(cost) = costs[i].filter(|&c| c < INF) {
+                title.push_str(&format!("\nminimum total cost: {}", cost));
+            }
+            let (x, y, width) = (x(i), y(i), width(i));
+            writeln!(w, "<g class=\"node {class}{best}\">")?;
+            writeln!(w, "<title>{}</title>", escape(&title))?;
+            writeln!(
+                w,
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{NODE_HEIGHT}\" rx=\"6\"/>"
+            )?;
+            writeln!(
+                w,
+                "<text x=\"{}\" y=\"{}\">{}</text>",
+                x + width / 2,
+                y + NODE_HEIGHT / 3,
+                escape(surface)
+            )?;
+            writeln!(
+                w,
+                "<text class=\"cost\" x=\"{}\" y=\"{}\">{}</text>",
+                x + width / 2,
+                y + NODE_HEIGHT * 3 / 4,
+                word_cost
+            )?;
+            writeln!(w, "</g>")?;
+        }
+        writeln!(w, "</svg>")?;
+        writeln!(w, "</body>")?;
+        writeln!(w, "</html>")
+    }
+}
@@ -1,6 +1,38 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use cost::{CostModel, DictCostModel};
 use kanpyo_dict::{dict::Dict, morph::Morph, trie::da::KeywordID};
 use node::Node;
+pub mod cost;
+#[cfg(feature = "serde")]
+pub mod json;
 pub mod node;
+pub(crate) const INF: i32 = 1 << 30;
+
+/// Explanation represents the cost breakdown of a node on the best path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub node: Node,
+    /// Cost of the word itself.
+    pub word_cost: i32,
+    /// Connection cost from the previous node on the best path.
+    pub connection_cost: i32,
+    /// Running total of the best path up to and including this node.
+    pub total_cost: i32,
+    /// The cheapest competitor ending at the same position, if any.
+    pub alternative: Option<Alternative>,
+}
+
+/// Alternative represents a node competing with a node on the best path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    pub node: Node,
+    /// Minimum cost to reach this node.
+    pub total_cost: i32,
+    /// How much more the best path would cost if it went through this node instead.
+    pub extra_cost: i32,
+}
+
 // Lattice represents a grid of morph nodes.
 #[derive(Debug, Clone)]
 pub struct Lattice<'a> {
@@ -43,57 +75,16 @@ impl<'a> Lattice<'a> {
         &mut self,
         byte_pos: usize,
         char_pos: usize,
-        ch: char,
         input: &str,
         matched_known: bool,
     ) {
-        // Determine the character category of `ch`.
-        let char_category = self.dict.char_category_def.char_category(ch);
-
-        // If no known matches or if the dictionary explicitly requires unknown processing, proceed.
-        if !matched_known || self.dict.char_category_def.invoke_list[char_category as usize] {
-            const MAXIMUM_UNKNOWN_WORD_LENGTH: usize = 1024;
-
-            // If this category supports grouping, we can bundle consecutive characters of the same type.
-            let is_group = *self
-                .dict
-                .char_category_def
-                .group_list
-                .get(char_category as usize)
-                .unwrap_or(&false);
-
-            // Determine how far the unknown sequence goes.
-            let mut end_byte_pos = byte_pos + ch.len_utf8();
-            let mut unknown_word_length = 1;
-
-            // Extend the unknown sequence while the next character is in the same category.
-            if is_group {
-                for next_char in input[end_byte_pos..].chars() {
-                    let next_category = self.dict.char_category_def.char_category(next_char);
-                    if next_category != char_category {
-                        break;
-                    }
-                    end_byte_pos += next_char.len_utf8();
-                    unknown_word_length += 1;
-
-                    // Limit the maximum length of unknown sequences.
-                    if unknown_word_length >= MAXIMUM_UNKNOWN_WORD_LENGTH {
-                        break;
-                    }
-                }
-            }
-
-            // Get the registered (morph_id, count) for this character category in the unknown dictionary.
-            if let Some(&(morph_id, count)) = self
-                .dict
-                .unk_dict
-                .char_category_to_morph_id
-                .get(&char_category)
-            {
-                let surface = &input[byte_pos..end_byte_pos];
-                for i in 0..count {
-                    self.add_unknown_node(morph_id + i as isize, byte_pos, char_pos, surface);
-                }
+        if let Some((morph_ids, byte_length)) = self
+            .dict
+            .search_unknown_words(&input[byte_pos..], matched_known)
+        {
+            let surface = &input[byte_pos..byte_pos + byte_length];
+            for morph_id in morph_ids {
+                self.add_unknown_node(morph_id, byte_pos, char_pos, surface);
             }
         }
     }
@@ -106,15 +97,18 @@ impl<'a> Lattice<'a> {
             // Known words
             let matched_known = la.process_known_words(byte_pos, char_pos, input);
             // Unknown words
-            la.process_unknown_words(byte_pos, char_pos, ch, input, matched_known);
+            la.process_unknown_words(byte_pos, char_pos, input, matched_known);
             byte_pos += ch.len_utf8();
         }
         la.add_eos_node(input);
         la
     }
     // runs forward algorithm of the Viterbi.
-    pub fn viterbi(&self) -> Vec<Node> {
-        const INF: i32 = 1 << 30;
+    // Returns the minimum cost to reach each node and the previous node on that path.
+    pub(crate) fn forward<C: CostModel + ?Sized>(
+        &self,
+        model: &C,
+    ) -> (Vec<Option<i32>>, Vec<Option<usize>>) {
         let mut dp = vec![None; self.nodes.len()];
         let mut pre_nodes = vec![None; self.nodes.len()];
         let char_len = self.edges.len();
@@ -122,30 +116,36 @@ impl<'a> Lattice<'a> {
             for (i, target) in self.edges[char_pos].iter().map(|&i| (i, &self.nodes[i])) {
                 dp[i] = Some(INF);
                 let char_pos = target.char_pos();
-                for (j, previous) in self.edges[char_pos].iter().map(|&i| (i, &self.nodes[i])) {
-                    // let cost = target.morph.as_ref().map_or(0, |m| m.cost) as i32;
+                for &j in self.edges[char_pos].iter() {
+                    // a node after a gap, which a lattice from JSON may have, isn't reachable
+                    if !self.is_reachable(&pre_nodes, j) {
+                        continue;
+                    }
                     let prev_cost = dp[j].unwrap_or(0);
-                    let previous_morph = previous.morph();
-                    let target_morph = target.morph();
-                    let cost = target_morph.cost as i32;
-                    let matrix_cost = self.dict.connection_table.get(
-                        previous_morph.right_id as usize,
-                        target_morph.left_id as usize,
-                    ) as i32;
-                    let total_cost = (prev_cost + cost + matrix_cost).min(INF);
-                    dp[i].is_none_or(|c| total_cost < c).then(|| {
+                    let cost = model.node_cost_at(self, i);
+                    let matrix_cost = model.edge_cost_at(self, j, i);
+                    let total_cost = prev_cost
+                        .saturating_add(cost)
+                        .saturating_add(matrix_cost)
+                        .min(INF);
+                    // the first candidate is taken even at INF, so a forbidden path is still the least bad one
+                    (pre_nodes[i].is_none() || dp[i].is_some_and(|c| total_cost < c)).then(|| {
                         dp[i] = Some(total_cost);
                         pre_nodes[i] = Some(j);
                     });
                 }
             }
         }
+        (dp, pre_nodes)
+    }
 
+    // returns indices of the nodes on the best path from the first word to EOS.
+    pub(crate) fn best_path(&self, pre_nodes: &[Option<usize>]) -> Vec<usize> {
         let mut pos = self.nodes.len() - 1;
         let mut paths = Vec::new();
 
         while let Some(pre) = pre_nodes[pos] {
-            paths.push(self.nodes[pos].clone());
+            paths.push(pos);
             pos = pre;
         }
 
@@ -153,6 +153,123 @@ impl<'a> Lattice<'a> {
         paths
     }
 
+    // returns whether a node is reached from BOS, which holds for BOS itself.
+    fn is_reachable(&self, pre_nodes: &[Option<usize>], i: usize) -> bool {
+        i == 0 || pre_nodes[i].is_some()
+    }
+
+    pub fn viterbi(&self) -> Vec<Node> {
+        self.viterbi_with(&DictCostModel)
+    }
+
+    /// Returns the best path under a cost model.
+    pub fn viterbi_with<C: CostModel + ?Sized>(&self, model: &C) -> Vec<Node> {
+        let (_, pre_nodes) = self.forward(model);
+        self.best_path(&pre_nodes)
+            .into_iter()
+            .map(|i| self.nodes[i].clone())
+            .collect()
+    }
+
+    /// Returns up to `n` best paths in ascending order of cost.
+    /// Each path runs from the first word to EOS like `viterbi`.
+    pub fn nbest(&self, n: usize) -> Vec<(Vec<Node>, i32)> {
+        self.nbest_with(n, &DictCostModel)
+    }
+
+    /// Returns up to `n` best paths under a cost model.
+    pub fn nbest_with<C: CostModel + ?Sized>(&self, n: usize, model: &C) -> Vec<(Vec<Node>, i32)> {
+        let (dp, pre_nodes) = self.forward(model);
+        // A* search from EOS to BOS. The forward cost is the exact heuristic,
+        // so complete paths are popped in ascending order of cost.
+        // Each state is a node with a link to the state it was extended from,
+        // so a path is rebuilt only when it reaches BOS.
+        let mut states: Vec<(usize, Option<usize>)> = Vec::new();
+        let mut heap = BinaryHeap::new();
+        let eos = self.nodes.len() - 1;
+        if self.is_reachable(&pre_nodes, eos) {
+            states.push((eos, None));
+            heap.push(Reverse((dp[eos].unwrap_or(0), 0i32, 0)));
+        }
+        let mut paths = Vec::new();
+        while let Some(Reverse((cost, backward_cost, state))) = heap.pop() {
+            if paths.len() >= n {
+                break;
+            }
+            let (i, parent) = states[state];
+            if i == 0 {
+                // reached BOS, and the links run from the first word to EOS
+                let path = std::iter::successors(parent, |&state| states[state].1)
+                    .map(|state| self.nodes[states[state].0].clone())
+                    .collect();
+                paths.push((path, cost));
+                continue;
+            }
+            for &j in self.edges[self.nodes[i].char_pos()].iter() {
+                if !self.is_reachable(&pre_nodes, j) {
+                    continue;
+                }
+                let backward_cost = backward_cost
+                    .saturating_add(model.node_cost_at(self, i))
+                    .saturating_add(model.edge_cost_at(self, j, i))
+                    .min(INF);
+                states.push((j, Some(state)));
+                heap.push(Reverse((
+                    dp[j].unwrap_or(0).saturating_add(backward_cost).min(INF),
+                    backward_cost,
+                    states.len() - 1,
+                )));
+            }
+        }
+        paths
+    }
+
+    /// Returns the cost breakdown of every node on the best path.
+    /// Each node also carries the cheapest competitor ending at the same position,
+    /// compared by the cost of reaching the next node on the best path.
+    pub fn explain(&self) -> Vec<Explanation> {
+        self.explain_with(&DictCostModel)
+    }
+
+    /// Returns the cost breakdown of every node on the best path under a cost model.
+    pub fn explain_with<C: CostModel + ?Sized>(&self, model: &C) -> Vec<Explanation> {
+        let (dp, pre_nodes) = self.forward(model);
+        let path = self.best_path(&pre_nodes);
+        let mut explanations = Vec::with_capacity(path.len());
+        for (k, &i) in path.iter().enumerate() {
+            let node = &self.nodes[i];
+            let previous = pre_nodes[i].expect("node on the best path has a previous node");
+            let alternative = path.get(k + 1).and_then(|&next| {
+                let next_node = &self.nodes[next];
+                let reach = |j: usize| {
+                    dp[j]
+                        .unwrap_or(0)
+                        .saturating_add(model.edge_cost_at(self, j, next))
+                        .min(INF)
+                };
+                let best = reach(i);
+                self.edges[next_node.char_pos()]
+                    .iter()
+                    .filter(|&&j| j != i && self.is_reachable(&pre_nodes, j))
+                    .map(|&j| (j, reach(j)))
+                    .min_by_key(|&(_, cost)| cost)
+                    .map(|(j, cost)| Alternative {
+                        node: self.nodes[j].clone(),
+                        total_cost: dp[j].unwrap_or(0),
+                        extra_cost: cost.saturating_sub(best),
+                    })
+            });
+            explanations.push(Explanation {
+                node: node.clone(),
+                word_cost: model.node_cost_at(self, i),
+                connection_cost: model.edge_cost_at(self, previous, i),
+                total_cost: dp[i].unwrap_or(0),
+                alternative,
+            });
+        }
+        explanations
+    }
+
     fn add_bos_node(&mut self) {
         let idx = self.nodes.len();
         self.nodes.push(node::Node::Dummy {
@@ -0,0 +1,46 @@
+use kanpyo_dict::dict::Dict;
+
+use super::{Lattice, node::Node};
+
+/// CostModel supplies the costs used to decode a lattice.
+/// The total cost of a path is the sum of its node costs and edge costs,
+/// and the path with the minimum total cost is the best.
+pub trait CostModel {
+    /// Returns the cost of a node.
+    fn node_cost(&self, dict: &Dict, node: &Node) -> i32;
+    /// Returns the cost of connecting `previous` to `target`.
+    fn edge_cost(&self, dict: &Dict, previous: &Node, target: &Node) -> i32;
+
+    /// Returns the cost of the `i`-th node of `lattice`, which the lattice uses while decoding.
+    /// It's `node_cost` unless the model scores the nodes of a particular lattice by index.
+    fn node_cost_at(&self, lattice: &Lattice, i: usize) -> i32 {
+        self.node_cost(lattice.dict, &lattice.nodes[i])
+    }
+
+    /// Returns the cost of connecting the `previous`-th node of `lattice` to the `target`-th one.
+    fn edge_cost_at(&self, lattice: &Lattice, previous: usize, target: usize) -> i32 {
+        self.edge_cost(
+            lattice.dict,
+            &lattice.nodes[previous],
+            &lattice.nodes[target],
+        )
+    }
+}
+
+/// DictCostModel is the cost model of the dictionary:
+/// word costs of morphs and connection costs of the connection table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DictCostModel;
+
+impl CostModel for DictCostModel {
+    fn node_cost(&self, _dict: &Dict, node: &Node) -> i32 {
+        node.morph().cost as i32
+    }
+
+    fn edge_cost(&self, dict: &Dict, previous: &Node, target: &Node) -> i32 {
+        dict.connection_table.get(
+            previous.morph().right_id as usize,
+            target.morph().left_id as usize,
+        ) as i32
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use kanpyo_dict::{dict::Dict, morph::Morph, trie::da::KeywordID};
+use serde::{Deserialize, Serialize, de::Error};
+
+use super::{
+    Lattice,
+    cost::{CostModel, DictCostModel},
+    node::{Node, Word},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeClass {
+    Dummy,
+    Known,
+    Unknown,
+}
+
+/// JsonNode is a lattice node in JSON.
+/// `cost` may be overridden and `score` is added to it when a lattice is loaded.
+/// `cost` must fit in i16 like the costs of the dictionary, while `score` is i32
+/// so that scores of an external model don't have to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonNode {
+    pub class: NodeClass,
+    pub id: KeywordID,
+    pub surface: String,
+    pub byte_pos: usize,
+    pub char_pos: usize,
+    pub left_id: i16,
+    pub right_id: i16,
+    pub cost: i32,
+    #[serde(default)]
+    pub score: i32,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+/// JsonEdgeScore is a score added to the connection cost from the `previous` node to the `next` one,
+/// which are indices of the nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonEdgeScore {
+    pub previous: usize,
+    pub next: usize,
+    pub score: i32,
+}
+
+/// JsonLattice is a lattice in JSON.
+/// `edges[i]` lists the indices of the nodes ending at the i-th character, as `Lattice::edges` does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonLattice {
+    pub nodes: Vec<JsonNode>,
+    pub edges: Vec<Vec<usize>>,
+    #[serde(default)]
+    pub edge_scores: Vec<JsonEdgeScore>,
+}
+
+/// JsonCostModel is the cost model of a lattice loaded from JSON.
+/// Word costs are `cost` plus `score` of the nodes, and connection costs are those of the dictionary
+/// plus the edge scores.
+/// Scores are kept by node index, so they apply only to the lattice loaded with the model,
+/// and `node_cost` and `edge_cost` without an index are those of the dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonCostModel {
+    nodes: Vec<i32>,
+    edges: BTreeMap<(usize, usize), i32>,
+}
+
+impl CostModel for JsonCostModel {
+    fn node_cost(&self, dict: &Dict, node: &Node) -> i32 {
+        DictCostModel.node_cost(dict, node)
+    }
+
+    fn edge_cost(&self, dict: &Dict, previous: &Node, target: &Node) -> i32 {
+        DictCostModel.edge_cost(dict, previous, target)
+    }
+
+    fn node_cost_at(&self, lattice: &Lattice, i: usize) -> i32 {
+        self.nodes
+            .get(i)
+            .copied()
+            .unwrap_or_else(|| DictCostModel.node_cost_at(lattice, i))
+    }
+
+    fn edge_cost_at(&self, lattice: &Lattice, previous: usize, target: usize) -> i32 {
+        let score = self.edges.get(&(previous, target)).copied().unwrap_or(0);
+        DictCostModel
+            .edge_cost_at(lattice, previous, target)
+            .saturating_add(score)
+    }
+}
+
+impl From<&Lattice<'_>> for JsonLattice {
+    fn from(lattice: &Lattice<'_>) -> Self {
+        let nodes = lattice
+            .nodes
+            .iter()
+            .map(|node| {
+                let (class, surface) = match node {
+                    Node::Dummy { .. } => (NodeClass::Dummy, String::new()),
+                    Node::Known(word) => (NodeClass::Known, word.surface.clone()),
+                    Node::Unknown(word) => (NodeClass::Unknown, word.surface.clone()),
+                };
+                let morph = node.morph();
+                JsonNode {
+                    class,
+                    id: node.id(),
+                    surface,
+                    byte_pos: node.byte_pos(),
+                    char_pos: node.char_pos(),
+                    left_id: morph.left_id,
+                    right_id: morph.right_id,
+                    cost: morph.cost as i32,
+                    score: 0,
+                    features: node
+                        .features(lattice.dict)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                }
+            })
+            .collect();
+        JsonLattice {
+            nodes,
+            edges: lattice.edges.clone(),
+            edge_scores: Vec::new(),
+        }
+    }
+}
+
+impl<'a> Lattice<'a> {
+    /// Serializes the lattice to JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&JsonLattice::from(self))
+    }
+
+    /// Loads a lattice serialized by `to_json` with the cost model of its scores,
+    /// which decodes it like `lattice.viterbi_with(&model)`.
+    /// The morphs of the nodes keep `cost`, which must fit in i16 like the costs of the dictionary,
+    /// and the model adds `score` to it in i32.
+    /// The first node must be BOS and the last one EOS, and `edges[i]` must list only nodes ending
+    /// at the i-th character.
+    pub fn from_json(dict: &'a Dict, json: &str) -> serde_json::Result<(Self, JsonCostModel)> {
+        let lattice: JsonLattice = serde_json::from_str(json)?;
+        let len = lattice.nodes.len();
+        if len < 2
+            || lattice.nodes[0].class != NodeClass::Dummy
+            || lattice.nodes[len - 1].class != NodeClass::Dummy
+        {
+            return Err(serde_json::Error::custom(
+                "the first and last nodes must be dummy nodes",
+            ));
+        }
+        if let Some(&i) = lattice.edges.iter().flatten().find(|&&i| i >= len) {
+            return Err(serde_json::Error::custom(format!(
+                "node index {} is out of range",
+                i
+            )));
+        }
+
+        // the char position where a node ends, which is its index in the edges.
+        // BOS ends at 0 and the other dummy nodes span a character like EOS.
+        let end = |i: usize, node: &JsonNode| match node.class {
+            NodeClass::Dummy if i == 0 => Some(0),
+            NodeClass::Dummy => node.char_pos.checked_add(1),
+            _ if node.surface.is_empty() => None,
+            _ => node.char_pos.checked_add(node.surface.chars().count()),
+        };
+        let table = &dict.connection_table;
+        if let Some(node) = lattice.nodes.iter().enumerate().find_map(|(i, node)| {
+            (end(i, node).is_none_or(|end| end >= lattice.edges.len())
+                || !(0..table.col() as i32).contains(&(node.left_id as i32))
+                || !(0..table.row() as i32).contains(&(node.right_id as i32)))
+            .then_some(node)
+        }) {
+            return Err(serde_json::Error::custom(format!(
+                "node {:?} at {} has an invalid position or context id",
+                node.surface, node.char_pos
+            )));
+        }
+        if let Some(node) = lattice
+            .nodes
+            .iter()
+            .find(|node| i16::try_from(node.cost).is_err())
+        {
+            return Err(serde_json::Error::custom(format!(
+                "node {:?} at {} has cost {} out of range of i16",
+                node.surface, node.char_pos, node.cost
+            )));
+        }
+        // forward and best_path rely on every node in edges[i] ending at i
+        for (pos, indices) in lattice.edges.iter().enumerate() {
+            if let Some(&i) = indices
+                .iter()
+                .find(|&&i| end(i, &lattice.nodes[i]) != Some(pos))
+            {
+                return Err(serde_json::Error::custom(format!(
+                    "node {} listed in edges[{}] doesn't end there",
+                    i, pos
+                )));
+            }
+        }
+
+        // an edge score connects a node to one ending where the node begins
+        if let Some(edge) = lattice.edge_scores.iter().find(|edge| {
+            edge.next == 0
+                || lattice.nodes.get(edge.next).is_none_or(|next| {
+                    lattice
+                        .edges
+                        .get(next.char_pos)
+                        .is_none_or(|indices| !indices.contains(&edge.previous))
+                })
+        }) {
+            return Err(serde_json::Error::custom(format!(
+                "edge score from node {} to node {} doesn't connect them",
+                edge.previous, edge.next
+            )));
+        }
+
+        let mut nodes = Vec::with_capacity(len);
+        let mut model = JsonCostModel::default();
+        for node in lattice.nodes {
+            // costs are checked above
+            let morph = Morph::new(node.left_id, node.right_id, node.cost as i16);
+            let word = || Word {
+                id: node.id,
+                byte_pos: node.byte_pos,
+                char_pos: node.char_pos,
+                morph: morph.clone(),
+                surface: node.surface.clone(),
+            };
+            nodes.push(match node.class {
+                NodeClass::Dummy => Node::Dummy {
+                    byte_pos: node.byte_pos,
+                    char_pos: node.char_pos,
+                    morph: morph.clone(),
+                },
+                NodeClass::Known => Node::Known(word()),
+                NodeClass::Unknown => Node::Unknown(word()),
+            });
+            model.nodes.push(node.cost.saturating_add(node.score));
+        }
+        for edge in lattice.edge_scores {
+            let score = model.edges.entry((edge.previous, edge.next)).or_default();
+            *score = score.saturating_add(edge.score);
+        }
+        Ok((
+            Self {
+                dict,
+                nodes,
+                edges: lattice.edges,
+            },
+            model,
+        ))
+    }
+}
@@ -1,4 +1,4 @@
-use kanpyo_dict::{morph::Morph, trie::da::KeywordID};
+use kanpyo_dict::{dict::Dict, morph::Morph, trie::da::KeywordID};
 
 pub const BOS_EOS_ID: KeywordID = 0;
 
@@ -50,4 +50,19 @@ impl Node {
             Node::Known(word) | Node::Unknown(word) => &word.morph,
         }
     }
+
+    pub fn features<'a>(&self, dict: &'a Dict) -> Vec<&'a str> {
+        match self {
+            Node::Dummy { .. } => Vec::new(),
+            Node::Known(word) => dict
+                .morph_feature_table
+                .features(word.id)
+                .unwrap_or_default(),
+            Node::Unknown(word) => dict
+                .unk_dict
+                .morph_feature_table
+                .features(word.id)
+                .unwrap_or_default(),
+        }
+    }
 }
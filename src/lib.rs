@@ -1,7 +1,21 @@
+pub mod adjustment;
+#[cfg(feature = "mecab-ipadic")]
+pub mod embedded;
+pub mod filter;
+pub mod format;
 pub mod graphviz;
+pub mod html;
 pub mod lattice;
+pub mod normalize;
+#[cfg(feature = "serde")]
+pub mod rpc;
+pub mod sentence;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod token;
 pub mod tokenizer;
+pub mod ud;
+pub mod wakati;
 
 #[cfg(test)]
 mod tests;
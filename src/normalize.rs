@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+
+use unicode_normalization::{UnicodeNormalization, char::canonical_combining_class};
+
+use crate::token::Token;
+
+/// Normalizer normalizes input before tokenization with NFKC, e.g. full-width ASCII,
+/// half-width katakana and compatibility characters like ㍿.
+///
+/// Rules override NFKC per character. Kept characters are left as they are, e.g. `～` which NFKC maps to `~`,
+/// and replaced characters are mapped to strings, e.g. `~` to `〜`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Normalizer {
+    keep: HashSet<char>,
+    replace: HashMap<char, String>,
+}
+
+impl Normalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves the characters as they are.
+    pub fn with_keep<I: IntoIterator<Item = char>>(mut self, chars: I) -> Self {
+        self.keep.extend(chars);
+        self
+    }
+
+    /// Maps a character to a string instead of its NFKC form.
+    pub fn with_replace(mut self, from: char, to: impl Into<String>) -> Self {
+        self.replace.insert(from, to.into());
+        self
+    }
+
+    // is_ruled returns whether NFKC is overridden for a character.
+    fn is_ruled(&self, ch: char) -> bool {
+        self.keep.contains(&ch) || self.replace.contains_key(&ch)
+    }
+
+    /// Normalizes `text` keeping the offsets of each normalized character in `text`.
+    pub fn normalize(&self, text: &str) -> Normalized {
+        let mut normalized = Normalized {
+            text: String::with_capacity(text.len()),
+            spans: Vec::new(),
+            byte_len: text.len(),
+            char_len: 0,
+        };
+        // A unit is a character followed by characters composed with it, e.g. ｶ and ﾞ to ガ.
+        // Each normalized character maps to the whole unit it comes from.
+        let mut unit = String::new();
+        let mut span = Span::default();
+        for (char_pos, (byte_pos, ch)) in text.char_indices().enumerate() {
+            let continues = !self.is_ruled(ch)
+                && !unit.is_empty()
+                && !unit.chars().next().is_some_and(|ch| self.is_ruled(ch))
+                && ch
+                    .nfkc()
+                    .next()
+                    .is_some_and(|ch| canonical_combining_class(ch) != 0);
+            if !continues {
+                self.flush(&unit, span, &mut normalized);
+                unit.clear();
+                span.byte_start = byte_pos;
+                span.char_start = char_pos;
+            }
+            unit.push(ch);
+            span.char_end = char_pos + 1;
+            normalized.char_len = char_pos + 1;
+        }
+        self.flush(&unit, span, &mut normalized);
+        normalized
+    }
+
+    // flush pushes a unit, which has a single character if it's ruled.
+    fn flush(&self, unit: &str, span: Span, normalized: &mut Normalized) {
+        match unit.chars().next() {
+            None => {}
+            Some(ch) if self.keep.contains(&ch) => self.push(unit.chars(), span, normalized),
+            Some(ch) => match self.replace.get(&ch) {
+                Some(to) => self.push(to.chars(), span, normalized),
+                None => self.push(unit.nfkc(), span, normalized),
+            },
+        }
+    }
+
+    fn push(&self, chars: impl Iterator<Item = char>, span: Span, normalized: &mut Normalized) {
+        for ch in chars {
+            normalized.text.push(ch);
+            normalized.spans.push(span);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Span {
+    byte_start: usize,
+    char_start: usize,
+    char_end: usize,
+}
+
+/// Normalized is a normalized text with the offsets of its characters in the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub text: String,
+    // the span in the original text of each character of `text`
+    spans: Vec<Span>,
+    byte_len: usize,
+    char_len: usize,
+}
+
+impl Normalized {
+    /// Maps a range of chars in the normalized text to a range of chars in the original text.
+    /// A range inside an expansion like 株式会社 from ㍿ maps to the whole original character.
+    pub fn char_range(&self, start: usize, end: usize) -> (usize, usize) {
+        let original_start = self
+            .spans
+            .get(start)
+            .map_or(self.char_len, |span| span.char_start);
+        let original_end = match end.checked_sub(1).and_then(|i| self.spans.get(i)) {
+            Some(span) if end > start => span.char_end,
+            _ => original_start,
+        };
+        (original_start, original_end)
+    }
+
+    /// Maps a char offset in the normalized text to a byte offset in the original text.
+    pub fn byte_pos(&self, char_pos: usize) -> usize {
+        self.spans
+            .get(char_pos)
+            .map_or(self.byte_len, |span| span.byte_start)
+    }
+
+    /// Maps the offsets of tokens of the normalized text to the original text.
+    /// Surfaces stay normalized, so use `Token::byte_range` for the bytes of a token in the original text.
+    pub fn restore(&self, tokens: Vec<Token>) -> Vec<Token> {
+        tokens
+            .into_iter()
+            .map(|mut token| {
+                let (start, end) = self.char_range(token.start, token.end);
+                token.position = self.byte_pos(token.start);
+                token.start = start;
+                token.end = end;
+                token
+            })
+            .collect()
+    }
+}
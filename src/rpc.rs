@@ -0,0 +1,223 @@
+use std::io::{BufRead, Write};
+
+use kanpyo_dict::dict::EntryClass;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, value::RawValue};
+
+use crate::{
+    token::{
+        TokenClass,
+        json::{JsonPath, JsonToken, NamedFeatures},
+    },
+    tokenizer::Tokenizer,
+};
+
+// Error codes of JSON-RPC 2.0.
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const INTERNAL_ERROR: i32 = -32603;
+
+/// Maximum `n` of the `nbest` method.
+pub const MAX_NBEST: usize = 100;
+
+/// Request is a JSON-RPC 2.0 request. A request without `id` is answered with a null `id`.
+/// `jsonrpc` may be omitted, but a request with a version other than `2.0` is invalid.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub jsonrpc: Option<String>,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Error {
+    pub code: i32,
+    pub message: String,
+}
+
+/// Response is a JSON-RPC 2.0 response, which has either `result` or `error`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Response {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Box<RawValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Error>,
+}
+
+impl Response {
+    fn new(id: Value, result: std::result::Result<Box<RawValue>, Error>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Response {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+fn error(code: i32, message: impl ToString) -> Error {
+    Error {
+        code,
+        message: message.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct TextParams {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct NbestParams {
+    text: String,
+    #[serde(default = "default_n")]
+    n: usize,
+}
+
+fn default_n() -> usize {
+    1
+}
+
+#[derive(Serialize)]
+struct JsonLookup<'a> {
+    class: TokenClass,
+    byte_pos: usize,
+    char_pos: usize,
+    surface: &'a str,
+    left_id: i16,
+    right_id: i16,
+    cost: i16,
+    features: NamedFeatures<'a>,
+}
+
+fn params<T: DeserializeOwned>(params: &Value) -> std::result::Result<T, Error> {
+    T::deserialize(params).map_err(|e| error(INVALID_PARAMS, e))
+}
+
+fn to_value<T: Serialize>(value: T) -> std::result::Result<Box<RawValue>, Error> {
+    serde_json::value::to_raw_value(&value).map_err(|e| error(INTERNAL_ERROR, e))
+}
+
+/// Handles a request with the methods below.
+///
+/// - `tokenize` takes `{"text": ...}` and returns tokens like `kanpyo tokenize --output json`.
+/// - `nbest` takes `{"text": ..., "n": ...}` and returns up to `n` paths of `cost` and `tokens`.
+///   `n` must be at most `MAX_NBEST`.
+/// - `lookup` takes `{"text": ...}` and returns dictionary entries matching at each position.
+pub fn handle(tokenizer: &Tokenizer, request: &Request) -> Response {
+    if let Some(version) = request
+        .jsonrpc
+        .as_deref()
+        .filter(|&version| version != "2.0")
+    {
+        return Response::new(
+            request.id.clone(),
+            Err(error(
+                INVALID_REQUEST,
+                format!("unsupported jsonrpc version: {}", version),
+            )),
+        );
+    }
+    let dict = &tokenizer.dict;
+    let result = match request.method.as_str() {
+        "tokenize" => params::<TextParams>(&request.params).and_then(|params| {
+            let tokens = tokenizer.tokenize(&params.text);
+            to_value(
+                tokens
+                    .iter()
+                    .filter(|token| token.class != TokenClass::Dummy)
+                    .map(|token| JsonToken::new(token, dict))
+                    .collect::<Vec<_>>(),
+            )
+        }),
+        "nbest" => params::<NbestParams>(&request.params).and_then(|params| {
+            if params.n > MAX_NBEST {
+                return Err(error(
+                    INVALID_PARAMS,
+                    format!("n must be at most {}", MAX_NBEST),
+                ));
+            }
+            let paths = tokenizer.tokenize_nbest(&params.text, params.n);
+            to_value(
+                paths
+                    .iter()
+                    .map(|(tokens, cost)| JsonPath::new(tokens, *cost, dict))
+                    .collect::<Vec<_>>(),
+            )
+        }),
+        "lookup" => params::<TextParams>(&request.params).and_then(|params| {
+            to_value(
+                dict.lookup(&params.text)
+                    .into_iter()
+                    .map(|lookup| JsonLookup {
+                        class: match lookup.class {
+                            EntryClass::Known => TokenClass::Known,
+                            EntryClass::Unknown => TokenClass::Unknown,
+                        },
+                        byte_pos: lookup.byte_pos,
+                        char_pos: lookup.char_pos,
+                        surface: lookup.entry.surface,
+                        left_id: lookup.entry.morph.left_id,
+                        right_id: lookup.entry.morph.right_id,
+                        cost: lookup.entry.morph.cost,
+                        features: NamedFeatures(lookup.entry.features),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        }),
+        method => Err(error(
+            METHOD_NOT_FOUND,
+            format!("method not found: {}", method),
+        )),
+    };
+    Response::new(request.id.clone(), result)
+}
+
+/// Handles a line of a request in JSON.
+pub fn handle_line(tokenizer: &Tokenizer, line: &str) -> Response {
+    let value = match serde_json::from_str::<Value>(line) {
+        Ok(value) => value,
+        Err(e) => return Response::new(Value::Null, Err(error(PARSE_ERROR, e))),
+    };
+    let id = value.get("id").cloned().unwrap_or_default();
+    match Request::deserialize(value) {
+        Ok(request) => handle(tokenizer, &request),
+        Err(e) => Response::new(id, Err(error(INVALID_REQUEST, e))),
+    }
+}
+
+/// Reads newline-delimited requests from `reader` and writes a response per line to `writer`
+/// until `reader` is closed. Empty lines are ignored, and a line that isn't UTF-8 is a parse error.
+pub fn serve<R: BufRead, W: Write>(
+    tokenizer: &Tokenizer,
+    mut reader: R,
+    mut writer: W,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(());
+        }
+        let response = match std::str::from_utf8(&buf) {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => handle_line(tokenizer, line),
+            Err(e) => Response::new(Value::Null, Err(error(PARSE_ERROR, e))),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writeln!(writer)?;
+        writer.flush()?;
+    }
+}
@@ -0,0 +1,154 @@
+/// Sentence is a sentence in a document with its offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sentence<'a> {
+    pub text: &'a str,
+    pub position: usize, // byte position
+    pub start: usize,    // char position
+    pub end: usize,      // char position
+}
+
+/// SentenceSplitter splits a document into sentences.
+///
+/// A sentence ends with a newline or terminal punctuation like 。, which takes following terminals
+/// and closing brackets like `！？」`. Terminals inside brackets like 「」 don't end a sentence,
+/// but a closing bracket right after a terminal does, e.g. `「はい。」「いいえ。」` has two sentences,
+/// unless hiragana follows it like `「はい。」と言った。`.
+/// Whitespace around sentences is trimmed, and empty sentences are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentenceSplitter {
+    terminals: Vec<char>,
+    brackets: Vec<(char, char)>,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        Self {
+            terminals: vec!['。', '！', '？', '!', '?'],
+            brackets: vec![('「', '」'), ('『', '』')],
+        }
+    }
+}
+
+impl SentenceSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the terminal punctuation ending sentences.
+    pub fn with_terminals<I: IntoIterator<Item = char>>(mut self, terminals: I) -> Self {
+        self.terminals = terminals.into_iter().collect();
+        self
+    }
+
+    /// Sets the pairs of opening and closing brackets.
+    pub fn with_brackets<I: IntoIterator<Item = (char, char)>>(mut self, brackets: I) -> Self {
+        self.brackets = brackets.into_iter().collect();
+        self
+    }
+
+    fn is_terminal(&self, ch: char) -> bool {
+        self.terminals.contains(&ch)
+    }
+
+    fn is_opening(&self, ch: char) -> bool {
+        self.brackets.iter().any(|(opening, _)| *opening == ch)
+    }
+
+    fn is_closing(&self, ch: char) -> bool {
+        self.brackets.iter().any(|(_, closing)| *closing == ch)
+    }
+
+    // track_terminal updates whether the last char other than closing brackets is a terminal.
+    fn track_terminal(&self, terminated: &mut bool, ch: char) {
+        if !self.is_closing(ch) {
+            *terminated = self.is_terminal(ch);
+        }
+    }
+
+    /// Splits `document` into sentences with offsets into it.
+    pub fn split<'a>(&self, document: &'a str) -> Vec<Sentence<'a>> {
+        let mut sentences = Vec::new();
+        let chars = document.char_indices().collect::<Vec<_>>();
+        // start of the current sentence in bytes and chars
+        let (mut position, mut start) = (0, 0);
+        let mut depth = 0usize;
+        // whether the last char before `i` other than closing brackets is a terminal
+        let mut terminated = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i].1;
+            let end = match ch {
+                '\n' | '\r' => {
+                    depth = 0;
+                    Some(i)
+                }
+                _ if self.is_opening(ch) => {
+                    depth += 1;
+                    None
+                }
+                _ if self.is_closing(ch) => {
+                    depth = depth.saturating_sub(1);
+                    // a terminal may be followed by nested closing brackets like 。」』
+                    // hiragana like と continues a sentence quoting a sentence
+                    let quoted = chars
+                        .get(i + 1)
+                        .is_some_and(|(_, ch)| matches!(ch, 'ぁ'..='ゖ'));
+                    (depth == 0 && terminated && !quoted).then_some(i + 1)
+                }
+                _ if depth == 0 && self.is_terminal(ch) => Some(i + 1),
+                _ => None,
+            };
+            let Some(mut end) = end else {
+                self.track_terminal(&mut terminated, ch);
+                i += 1;
+                continue;
+            };
+            // terminals and closing brackets following the end belong to the sentence
+            while ch != '\n'
+                && ch != '\r'
+                && end < chars.len()
+                && (self.is_terminal(chars[end].1) || (depth == 0 && self.is_closing(chars[end].1)))
+            {
+                end += 1;
+            }
+            let byte_end = chars.get(end).map_or(document.len(), |(pos, _)| *pos);
+            push(&mut sentences, document, position, start, byte_end);
+            // a newline is skipped
+            let next = if end == i { end + 1 } else { end };
+            for &(_, ch) in &chars[i..next] {
+                self.track_terminal(&mut terminated, ch);
+            }
+            (position, start) = (
+                chars.get(next).map_or(document.len(), |(pos, _)| *pos),
+                next,
+            );
+            i = next;
+        }
+        push(&mut sentences, document, position, start, document.len());
+        sentences
+    }
+}
+
+// push pushes a sentence between bytes `position..byte_end` trimming whitespace.
+fn push<'a>(
+    sentences: &mut Vec<Sentence<'a>>,
+    document: &'a str,
+    position: usize,
+    start: usize,
+    byte_end: usize,
+) {
+    let text = &document[position..byte_end];
+    let trimmed = text.trim_start();
+    let start = start + text[..text.len() - trimmed.len()].chars().count();
+    let position = position + text.len() - trimmed.len();
+    let text = trimmed.trim_end();
+    if text.is_empty() {
+        return;
+    }
+    sentences.push(Sentence {
+        text,
+        position,
+        start,
+        end: start + text.chars().count(),
+    });
+}
@@ -0,0 +1,362 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use kanpyo_dict::error::{KanpyoError, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    format::Format,
+    graphviz::Graphviz,
+    html::Html,
+    lattice::Lattice,
+    token::json::JsonPath,
+    tokenizer::Tokenizer,
+    ud::{self, Tagset},
+};
+
+/// Mode of tokenization.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// The best path
+    #[default]
+    Normal,
+    /// Up to `n` best paths with their costs
+    Nbest,
+}
+
+/// TokenizeRequest is the body of `POST /tokenize`.
+///
+/// `output` is `json`, `wakati`, `conllu` or a format name of `Format::named` like `lattice` or `chasen`.
+/// Only `json` is available in the n-best mode.
+/// Offsets of format names point into the text normalized by the normalizer of the tokenizer if any.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TokenizeRequest {
+    pub text: String,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default = "default_n")]
+    pub n: usize,
+    #[serde(default = "default_output")]
+    pub output: String,
+}
+
+fn default_n() -> usize {
+    1
+}
+
+fn default_output() -> String {
+    "json".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatticeFormat {
+    #[default]
+    Dot,
+    Html,
+    Json,
+}
+
+/// LatticeRequest is the body of `POST /lattice`. `dpi`, `full_state` and `nbest` apply to `dot`.
+/// The lattice is built on the text normalized by the normalizer of the tokenizer if any.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LatticeRequest {
+    pub text: String,
+    #[serde(default)]
+    pub format: LatticeFormat,
+    #[serde(default = "default_dpi")]
+    pub dpi: usize,
+    #[serde(default)]
+    pub full_state: bool,
+    #[serde(default = "default_n")]
+    pub nbest: usize,
+}
+
+fn default_dpi() -> usize {
+    48
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status: 200,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::ok("application/json", body),
+            Err(e) => Self::error(500, e),
+        }
+    }
+
+    fn error(status: u16, error: impl ToString) -> Self {
+        Response {
+            status,
+            ..Self::json(&ErrorBody {
+                error: error.to_string(),
+            })
+        }
+    }
+}
+
+/// ShutdownHandle stops a running server from another thread, e.g. a signal handler.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    http: Arc<tiny_http::Server>,
+    stopped: Arc<AtomicBool>,
+    workers: usize,
+}
+
+impl ShutdownHandle {
+    /// Stops accepting requests. Requests being handled are completed.
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // each call unblocks one worker waiting for a request
+        for _ in 0..self.workers {
+            self.http.unblock();
+        }
+    }
+}
+
+/// Server serves a tokenizer over HTTP with a JSON API.
+///
+/// - `GET /health` returns `ok`.
+/// - `POST /tokenize` takes a `TokenizeRequest` and returns tokens.
+/// - `POST /lattice` takes a `LatticeRequest` and returns the lattice in Graphviz, HTML or JSON.
+///
+/// Requests are handled concurrently by worker threads sharing the tokenizer.
+/// `n` and `nbest` over `max_nbest` are rejected with 400 like the `nbest` method of `rpc`,
+/// and bodies over `max_body_size` bytes are rejected with 413.
+pub struct Server {
+    http: Arc<tiny_http::Server>,
+    stopped: Arc<AtomicBool>,
+    workers: usize,
+    max_nbest: usize,
+    max_body_size: usize,
+}
+
+pub const DEFAULT_MAX_NBEST: usize = crate::rpc::MAX_NBEST;
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, workers: usize) -> Result<Self> {
+        let http =
+            tiny_http::Server::http(addr).map_err(|e| KanpyoError::Io(std::io::Error::other(e)))?;
+        Ok(Server {
+            http: Arc::new(http),
+            stopped: Arc::new(AtomicBool::new(false)),
+            workers: workers.max(1),
+            max_nbest: DEFAULT_MAX_NBEST,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        })
+    }
+
+    /// Sets the maximum number of paths of the n-best mode and lattices.
+    pub fn with_max_nbest(mut self, max_nbest: usize) -> Self {
+        self.max_nbest = max_nbest.max(1);
+        self
+    }
+
+    /// Sets the maximum size of a request body in bytes.
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Returns the bound address, which is useful when binding port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            http: Arc::clone(&self.http),
+            stopped: Arc::clone(&self.stopped),
+            workers: self.workers,
+        }
+    }
+
+    /// Serves requests until `ShutdownHandle::shutdown` is called.
+    pub fn run(&self, tokenizer: &Tokenizer) {
+        std::thread::scope(|scope| {
+            for _ in 0..self.workers {
+                scope.spawn(|| {
+                    while !self.stopped.load(Ordering::SeqCst) {
+                        let Ok(request) = self.http.recv() else {
+                            break;
+                        };
+                        self.serve(tokenizer, request);
+                    }
+                });
+            }
+        });
+    }
+
+    fn serve(&self, tokenizer: &Tokenizer, mut request: tiny_http::Request) {
+        use std::io::Read;
+
+        let mut body = String::new();
+        // a chunked body has no length, so one more byte than the limit is read to detect it
+        let limit = self.max_body_size as u64 + 1;
+        let response = if request
+            .body_length()
+            .is_some_and(|len| len > self.max_body_size)
+        {
+            Response::error(413, "request body is too large")
+        } else {
+            match request.as_reader().take(limit).read_to_string(&mut body) {
+                Ok(_) if body.len() > self.max_body_size => {
+                    Response::error(413, "request body is too large")
+                }
+                Ok(_) => handle(
+                    tokenizer,
+                    self.max_nbest,
+                    request.method(),
+                    request.url().split('?').next().unwrap_or_default(),
+                    &body,
+                ),
+                Err(e) => Response::error(400, e),
+            }
+        };
+        let header = tiny_http::Header::from_bytes("Content-Type", response.content_type)
+            .expect("valid header");
+        // the client may have gone away
+        let _ = request.respond(
+            tiny_http::Response::from_data(response.body)
+                .with_status_code(response.status)
+                .with_header(header),
+        );
+    }
+}
+
+fn handle(
+    tokenizer: &Tokenizer,
+    max_nbest: usize,
+    method: &tiny_http::Method,
+    path: &str,
+    body: &str,
+) -> Response {
+    use tiny_http::Method;
+    match (method, path) {
+        (Method::Get, "/health") => Response::ok("text/plain; charset=utf-8", "ok"),
+        (Method::Post, "/tokenize") => match serde_json::from_str::<TokenizeRequest>(body) {
+            Ok(request) if request.n > max_nbest => {
+                Response::error(400, format!("n must be at most {}", max_nbest))
+            }
+            Ok(request) => tokenize(tokenizer, &request),
+            Err(e) => Response::error(400, e),
+        },
+        (Method::Post, "/lattice") => match serde_json::from_str::<LatticeRequest>(body) {
+            Ok(request) if request.nbest > max_nbest => {
+                Response::error(400, format!("nbest must be at most {}", max_nbest))
+            }
+            Ok(request) => lattice(tokenizer, &request),
+            Err(e) => Response::error(400, e),
+        },
+        (_, "/health" | "/tokenize" | "/lattice") => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn tokenize(tokenizer: &Tokenizer, request: &TokenizeRequest) -> Response {
+    let text = request.text.as_str();
+    let dict = &tokenizer.dict;
+    match (request.mode, request.output.as_str()) {
+        (Mode::Nbest, "json") => {
+            let paths = tokenizer.tokenize_nbest(text, request.n);
+            Response::json(
+                &paths
+                    .iter()
+                    .map(|(tokens, cost)| JsonPath::new(tokens, *cost, dict))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        (Mode::Nbest, _) => Response::error(400, "the nbest mode supports only json output"),
+        (Mode::Normal, "json") => {
+            match crate::token::json::to_json(&tokenizer.tokenize(text), dict) {
+                Ok(json) => Response::ok("application/json", json),
+                Err(e) => Response::error(500, e),
+            }
+        }
+        (Mode::Normal, "wakati") => {
+            Response::ok("text/plain; charset=utf-8", tokenizer.wakati(text))
+        }
+        (Mode::Normal, "conllu") => {
+            let mut buf = Vec::new();
+            match ud::write_conllu(
+                &mut buf,
+                dict,
+                Tagset::detect(dict),
+                None,
+                text,
+                &tokenizer.tokenize(text),
+            ) {
+                Ok(()) => Response::ok("text/plain; charset=utf-8", buf),
+                Err(e) => Response::error(500, e),
+            }
+        }
+        (Mode::Normal, name) => {
+            let format = match Format::named(name, None) {
+                Ok(format) => format,
+                Err(e) => return Response::error(400, e),
+            };
+            // the offsets of explanations point into the normalized text
+            let mut buf = Vec::new();
+            let normalized = tokenizer.normalize(text);
+            match format.write(&mut buf, dict, &normalized, &tokenizer.explain(text)) {
+                Ok(()) => Response::ok("text/plain; charset=utf-8", buf),
+                Err(e) => Response::error(500, e),
+            }
+        }
+    }
+}
+
+fn lattice(tokenizer: &Tokenizer, request: &LatticeRequest) -> Response {
+    let text = tokenizer.normalize(&request.text);
+    let lattice = Lattice::build(&tokenizer.dict, &text);
+    let mut buf = Vec::new();
+    let (content_type, result) = match request.format {
+        LatticeFormat::Dot => (
+            "text/vnd.graphviz; charset=utf-8",
+            Graphviz { lattice }.graphviz_with(
+                &mut buf,
+                request.dpi,
+                request.full_state,
+                request.nbest,
+                tokenizer.model(),
+            ),
+        ),
+        LatticeFormat::Html => (
+            "text/html; charset=utf-8",
+            Html { lattice }.html_with(&mut buf, &text, tokenizer.model()),
+        ),
+        LatticeFormat::Json => match lattice.to_json() {
+            Ok(json) => return Response::ok("application/json", json),
+            Err(e) => return Response::error(500, e),
+        },
+    };
+    match result {
+        Ok(()) => Response::ok(content_type, buf),
+        Err(e) => Response::error(500, e),
+    }
+}
@@ -1,8 +1,17 @@
+use crate::adjustment::CostAdjustments;
+use crate::format::{Dicrc, Format, Template};
+use crate::graphviz::Graphviz;
+use crate::html::Html;
+use crate::lattice::{
+    Lattice,
+    cost::{CostModel, DictCostModel},
+    node::Node,
+};
 use crate::token::TokenClass;
 use crate::tokenizer::Tokenizer;
 use kanpyo_dict::builder::matrix_def::MatrixDef;
 use kanpyo_dict::dict::Dict;
-use kanpyo_dict::{char_category_def, connection, index, morph, morph_feature, unk_dict};
+use kanpyo_dict::{char_category_def, connection, index, morph, morph_feature, surface, unk_dict};
 
 /// Creates a minimal test dictionary for testing purposes
 fn create_test_dict() -> Dict {
@@ -104,6 +113,7 @@ fn create_test_dict() -> Dict {
         index,
         char_category_def,
         unk_dict,
+        surface::SurfaceTable::build(&sorted_keywords),
     )
 }
 
@@ -200,3 +210,956 @@ fn test_tokenizer_dict_roundtrip() {
         assert_eq!(t1.class, t2.class, "Tokens should have same class");
     }
 }
+
+#[test]
+fn test_lattice_explain() {
+    let dict = create_test_dict();
+    let lattice = Lattice::build(&dict, "テスト辞書あいう");
+    let explanations = lattice.explain();
+
+    // the explained nodes are the best path
+    assert_eq!(
+        explanations
+            .iter()
+            .map(|e| e.node.clone())
+            .collect::<Vec<_>>(),
+        lattice.viterbi()
+    );
+
+    // the running total accumulates word and connection costs
+    let mut total = 0;
+    for explanation in &explanations {
+        total += explanation.word_cost + explanation.connection_cost;
+        assert_eq!(explanation.total_cost, total);
+        if let Some(alternative) = &explanation.alternative {
+            assert!(alternative.extra_cost >= 0);
+            assert_ne!(alternative.node, explanation.node);
+        }
+    }
+    // EOS has no competitor
+    assert!(explanations.last().unwrap().alternative.is_none());
+}
+
+#[test]
+fn test_lattice_nbest() {
+    let dict = create_test_dict();
+    let lattice = Lattice::build(&dict, "テスト辞書");
+    let paths = lattice.nbest(5);
+
+    // 辞書 is either a known word or a group of unknown kanji
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].0, lattice.viterbi());
+    assert_eq!(
+        paths[0].1,
+        lattice.explain().last().unwrap().total_cost,
+        "the best cost should match the Viterbi cost"
+    );
+    assert!(paths[0].1 <= paths[1].1);
+    assert_ne!(paths[0].0, paths[1].0);
+
+    assert_eq!(lattice.nbest(1).len(), 1);
+    assert!(lattice.nbest(0).is_empty());
+}
+
+#[test]
+fn test_graphviz_nbest() {
+    let dict = create_test_dict();
+    let graphviz = Graphviz {
+        lattice: Lattice::build(&dict, "テスト辞書"),
+    };
+
+    let mut buf = Vec::new();
+    graphviz
+        .graphviz(&mut buf, 48, false, 1)
+        .expect("Failed to write graphviz");
+    let dot = String::from_utf8(buf).unwrap();
+    assert!(dot.starts_with("graph lattice {"));
+    assert!(dot.trim_end().ends_with('}'));
+    // word cost of 辞書 and the bold best path
+    assert!(dot.contains("辞書\n名詞/一般/辞書/ジショ/ジショ\n1200"));
+    assert!(dot.contains("style=bold, color=\"blue\""));
+    assert!(!dot.contains("#e6550d"));
+
+    // the second best path goes through the unknown 辞書
+    let mut buf = Vec::new();
+    graphviz
+        .graphviz(&mut buf, 48, false, 2)
+        .expect("Failed to write graphviz");
+    let dot = String::from_utf8(buf).unwrap();
+    assert!(dot.contains("辞書\n未知語\n5000\", shape=diamond, color=\"#e6550d\""));
+    assert!(dot.contains("style=bold, color=\"#e6550d\""));
+
+    // the unknown 辞書 is the best under a cost model preferring it
+    let mut buf = Vec::new();
+    graphviz
+        .graphviz_with(&mut buf, 48, false, 1, &PreferUnknown)
+        .expect("Failed to write graphviz");
+    let dot = String::from_utf8(buf).unwrap();
+    assert!(dot.contains("辞書\n未知語\n-95000\", shape=ellipse, color=red, peripheries=2"));
+}
+
+#[test]
+fn test_html() {
+    let dict = create_test_dict();
+    let html = Html {
+        lattice: Lattice::build(&dict, "テスト辞書"),
+    };
+
+    let mut buf = Vec::new();
+    html.html(&mut buf, "テスト辞書")
+        .expect("Failed to write html");
+    let page = String::from_utf8(buf).unwrap();
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<svg"));
+    assert!(page.trim_end().ends_with("</html>"));
+    // the known 辞書 is on the best path and the unknown one is not
+    assert!(page.contains("<g class=\"node known best\">\n<title>辞書\n名詞,一般,*,*,*,*,辞書,ジショ,ジショ\nword cost: 1200"));
+    assert!(page.contains("<g class=\"node unknown\">\n<title>辞書\n未知語"));
+    assert_eq!(page.matches("class=\"edge best\"").count(), 3);
+
+    // costs and the best path follow the cost model
+    let mut buf = Vec::new();
+    html.html_with(&mut buf, "テスト辞書", &PreferUnknown)
+        .expect("Failed to write html");
+    let page = String::from_utf8(buf).unwrap();
+    assert!(page.contains("<g class=\"node unknown best\">\n<title>辞書\n未知語"));
+    assert!(page.contains("word cost: -95000"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_lattice_json_roundtrip() {
+    let dict = create_test_dict();
+    let lattice = Lattice::build(&dict, "テスト辞書");
+    let json = lattice.to_json().expect("Failed to serialize lattice");
+    let (loaded, model) = Lattice::from_json(&dict, &json).expect("Failed to load lattice");
+    assert_eq!(loaded.nodes, lattice.nodes);
+    assert_eq!(loaded.edges, lattice.edges);
+    assert_eq!(loaded.viterbi(), lattice.viterbi());
+    assert_eq!(loaded.viterbi_with(&model), lattice.viterbi());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_lattice_json_rescoring() {
+    use crate::lattice::json::{JsonEdgeScore, JsonLattice, NodeClass};
+
+    let dict = create_test_dict();
+    let lattice = Lattice::build(&dict, "テスト辞書");
+    let best = lattice.viterbi();
+    assert!(matches!(&best[1], Node::Known(word) if word.surface == "辞書"));
+
+    // penalize the known 辞書 beyond i16 so that the unknown one wins
+    let valid: JsonLattice = serde_json::from_str(&lattice.to_json().unwrap()).unwrap();
+    let known = valid
+        .nodes
+        .iter()
+        .position(|node| node.surface == "辞書" && node.class == NodeClass::Known)
+        .unwrap();
+    let mut json = valid.clone();
+    json.nodes[known].score = 100000;
+    let (rescored, model) =
+        Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).unwrap();
+    assert!(
+        matches!(&rescored.viterbi_with(&model)[1], Node::Unknown(word) if word.surface == "辞書")
+    );
+    // the morph keeps the cost, and only the model has the score
+    assert_eq!(
+        rescored.nodes[known].morph().cost as i32,
+        valid.nodes[known].cost
+    );
+    assert_eq!(rescored.viterbi(), best);
+
+    // a score far below i16 brings the known one back
+    json.nodes[known].score = -200000;
+    let (rescored, model) =
+        Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).unwrap();
+    assert!(
+        matches!(&rescored.viterbi_with(&model)[1], Node::Known(word) if word.surface == "辞書")
+    );
+    // while a cost out of range of i16 is rejected
+    json.nodes[known].cost = -200000;
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+
+    // penalize the edge from テスト to the known 辞書
+    let mut json = valid.clone();
+    json.edge_scores.push(JsonEdgeScore {
+        previous: 1,
+        next: known,
+        score: 100000,
+    });
+    let (rescored, model) =
+        Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).unwrap();
+    assert!(
+        matches!(&rescored.viterbi_with(&model)[1], Node::Unknown(word) if word.surface == "辞書")
+    );
+    // an edge score must connect nodes
+    json.edge_scores[0].previous = 0;
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+    json.edge_scores[0].next = valid.nodes.len();
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+
+    // invalid node indices are rejected
+    assert!(Lattice::from_json(&dict, r#"{"nodes": [], "edges": [[3]]}"#).is_err());
+
+    // nodes after a gap aren't reachable, so no path runs through them
+    let mut json = valid.clone();
+    json.edges[3].clear();
+    let (gapped, model) =
+        Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).unwrap();
+    assert!(gapped.viterbi_with(&model).is_empty());
+    assert!(gapped.nbest_with(2, &model).is_empty());
+    assert!(gapped.explain_with(&model).is_empty());
+
+    // nodes must fit in the edges and be listed where they end
+    let mut json = valid.clone();
+    json.nodes[1].surface = "テスト辞書形態素".to_string();
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+    let mut json = valid.clone();
+    json.nodes[1].surface = String::new();
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+    let mut json = valid.clone();
+    json.edges.swap(3, 5);
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+    let mut json = valid;
+    json.edges[3].push(0);
+    assert!(Lattice::from_json(&dict, &serde_json::to_string(&json).unwrap()).is_err());
+}
+
+/// Prefers unknown words by discounting them.
+struct PreferUnknown;
+
+impl CostModel for PreferUnknown {
+    fn node_cost(&self, dict: &Dict, node: &Node) -> i32 {
+        let cost = DictCostModel.node_cost(dict, node);
+        match node {
+            Node::Unknown(_) => cost - 100000,
+            _ => cost,
+        }
+    }
+
+    fn edge_cost(&self, dict: &Dict, previous: &Node, target: &Node) -> i32 {
+        DictCostModel.edge_cost(dict, previous, target)
+    }
+}
+
+#[test]
+fn test_tokenize_with_cost_model() {
+    let dict = create_test_dict();
+    let tokenizer = Tokenizer::new(dict);
+
+    assert_eq!(
+        tokenizer.tokenize_with("テスト辞書", &DictCostModel),
+        tokenizer.tokenize("テスト辞書")
+    );
+    let tokens = tokenizer.tokenize_with("テスト辞書", &PreferUnknown);
+    assert_eq!(tokens[1].surface, "辞書");
+    assert_eq!(tokens[1].class, TokenClass::Unknown);
+
+    // the breakdown follows the cost model
+    let lattice = Lattice::build(&tokenizer.dict, "テスト辞書");
+    let explanations = lattice.explain_with(&PreferUnknown);
+    assert_eq!(explanations[1].word_cost, 5000 - 100000);
+    let model: &dyn CostModel = &PreferUnknown;
+    assert_eq!(
+        lattice.nbest_with(1, model)[0].0,
+        lattice.viterbi_with(model)
+    );
+}
+
+#[test]
+fn test_cost_adjustments_parse() {
+    let adjustments = CostAdjustments::parse(
+        "# comment\nfeature\t名詞,一般\t500\n\nsurface 辞書 -100\nbigram 名詞 未知語 1000\n",
+    )
+    .expect("Failed to parse adjustments");
+    assert_eq!(
+        adjustments.features,
+        vec![(vec!["名詞".to_string(), "一般".to_string()], 500)]
+    );
+    assert_eq!(adjustments.surfaces.get("辞書"), Some(&-100));
+    assert_eq!(
+        adjustments.bigrams,
+        vec![(vec!["名詞".to_string()], vec!["未知語".to_string()], 1000)]
+    );
+
+    assert!(CostAdjustments::parse("feature 名詞").is_err());
+    assert!(CostAdjustments::parse("surface 辞書 x").is_err());
+    assert!(CostAdjustments::parse("unknown a 1").is_err());
+}
+
+#[test]
+fn test_tokenize_with_adjustments() {
+    let dict = create_test_dict();
+    let tokenizer = Tokenizer::new(dict);
+    let tokens = tokenizer.tokenize("テスト辞書");
+    assert_eq!(tokens[1].class, TokenClass::Known);
+
+    // penalize common nouns so that the unknown 辞書 wins
+    let tokenizer =
+        tokenizer.with_adjustments(CostAdjustments::parse("feature 名詞,一般 10000").unwrap());
+    let tokens = tokenizer.tokenize("テスト辞書");
+    assert_eq!(tokens[1].class, TokenClass::Unknown);
+
+    // the unknown 辞書 follows a noun, so a bigram penalty brings the known one back
+    let tokenizer = tokenizer.with_adjustments(
+        CostAdjustments::parse("feature 名詞,一般 10000\nbigram 名詞 未知語 20000").unwrap(),
+    );
+    let tokens = tokenizer.tokenize("テスト辞書");
+    assert_eq!(tokens[1].class, TokenClass::Known);
+
+    let tokenizer =
+        tokenizer.with_adjustments(CostAdjustments::parse("surface 辞書 -500").unwrap());
+    let lattice = Lattice::build(&tokenizer.dict, "テスト辞書");
+    let explanations = lattice.explain_with(&tokenizer.adjustments);
+    assert_eq!(explanations[0].word_cost, 1000);
+    assert_eq!(explanations[1].word_cost, 1200 - 500);
+
+    // large deltas saturate instead of overflowing
+    let adjustments =
+        CostAdjustments::parse("feature 名詞 2147483647\nsurface 辞書 2147483647").unwrap();
+    let node = lattice
+        .nodes
+        .iter()
+        .find(|node| matches!(node, Node::Known(word) if word.surface == "辞書"))
+        .unwrap();
+    assert_eq!(adjustments.node_cost(&tokenizer.dict, node), i32::MAX);
+    let adjustments = CostAdjustments::parse("feature 名詞 -2147483648").unwrap();
+    assert!(!lattice.nbest_with(3, &adjustments).is_empty());
+    assert!(!lattice.explain_with(&adjustments).is_empty());
+
+    // a forbidding delta still gives the least bad path
+    let tokenizer =
+        tokenizer.with_adjustments(CostAdjustments::parse("bigram 名詞 名詞 1100000000").unwrap());
+    let tokens = tokenizer.tokenize("テスト辞書");
+    assert_eq!(
+        tokens
+            .iter()
+            .map(|t| t.surface.as_str())
+            .collect::<Vec<_>>(),
+        vec!["テスト", "辞書", "EOS"]
+    );
+    let paths = tokenizer.tokenize_nbest("テスト辞書", 2);
+    assert_eq!(paths[0].0, tokens);
+    assert_eq!(tokenizer.explain("テスト辞書").len(), 3);
+}
+
+fn format_to_string(format: &Format, tokenizer: &Tokenizer, input: &str) -> String {
+    let mut out = vec![];
+    format
+        .write(&mut out, &tokenizer.dict, input, &tokenizer.explain(input))
+        .expect("Failed to write");
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_format() {
+    let tokenizer = Tokenizer::new(create_test_dict());
+    assert_eq!(
+        format_to_string(&Format::lattice(), &tokenizer, "テスト辞書"),
+        "テスト\t名詞,一般,*,*,*,*,テスト,テスト,テスト\n辞書\t名詞,一般,*,*,*,*,辞書,ジショ,ジショ\nEOS\n"
+    );
+    assert_eq!(
+        format_to_string(&Format::wakati(), &tokenizer, "テスト辞書"),
+        "テスト 辞書 \n"
+    );
+    assert_eq!(
+        format_to_string(
+            &Format::named("chasen", None).unwrap(),
+            &tokenizer,
+            "テスト辞書"
+        ),
+        "テスト\tテスト\tテスト\t名詞-一般\t*\t*\n辞書\tジショ\t辞書\t名詞-一般\t*\t*\nEOS\n"
+    );
+
+    let format = Format {
+        bos: "%s:%S(%L)\n".parse().unwrap(),
+        unk: "?%m\n".parse().unwrap(),
+        eos: "%s:%pC,%pc\n".parse().unwrap(),
+        ..Format::new("%s:%m[%pw,%pC,%pn,%pc] %ps-%pe %phl/%phr %f[0,9] %%\n").unwrap()
+    };
+    assert_eq!(
+        format_to_string(&format, &tokenizer, "テスト辞書"),
+        "2:テスト辞書(15)\n0:テスト[1000,0,1000,1000] 0-9 0/0 名詞,* %\n0:辞書[1200,100,1300,2300] 9-15 1/1 名詞,* %\n3:100,2400\n"
+    );
+    assert_eq!(
+        format_to_string(&format, &tokenizer, "テスト未知"),
+        "2:テスト未知(15)\n0:テスト[1000,0,1000,1000] 0-9 0/0 名詞,* %\n?未知\n3:0,6000\n"
+    );
+
+    // %pS is the white spaces skipped before a node, which a dictionary may not tokenize
+    let mut explanations = tokenizer.explain("テスト辞書");
+    if let Node::Known(word) = &mut explanations[1].node {
+        word.byte_pos += 1;
+    }
+    let mut out = vec![];
+    Format::new("[%pS]%m\n")
+        .unwrap()
+        .write(&mut out, &tokenizer.dict, "テスト 辞書", &explanations)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "[]テスト\n[ ]辞書\nEOS\n");
+
+    assert!("%q".parse::<Template>().is_err());
+    assert!("%f[a]".parse::<Template>().is_err());
+    assert!("%f[0".parse::<Template>().is_err());
+    assert!("%px".parse::<Template>().is_err());
+}
+
+#[test]
+fn test_dicrc() {
+    let dicrc = Dicrc::parse(
+        "; comment\ncost-factor = 800\nnode-format = %m\\n\nnode-format-surface = [%m]\neos-format-surface = \\n\n",
+    );
+    assert_eq!(dicrc.get("cost-factor"), Some("800"));
+    let tokenizer = Tokenizer::new(create_test_dict());
+    assert_eq!(
+        format_to_string(&dicrc.format("").unwrap(), &tokenizer, "テスト辞書"),
+        "テスト\n辞書\nEOS\n"
+    );
+    assert_eq!(
+        format_to_string(
+            &Format::named("surface", Some(&dicrc)).unwrap(),
+            &tokenizer,
+            "テスト辞書"
+        ),
+        "[テスト][辞書]\n"
+    );
+    assert!(dicrc.format("chasen").is_err());
+    assert!(Format::named("chasen", None).is_ok());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_token_json() {
+    use crate::token::{Token, json};
+    let tokenizer = Tokenizer::new(create_test_dict());
+    let tokens = tokenizer.tokenize("辞書");
+    assert_eq!(
+        tokens[0].features(&tokenizer.dict),
+        vec![
+            "名詞",
+            "一般",
+            "*",
+            "*",
+            "*",
+            "*",
+            "辞書",
+            "ジショ",
+            "ジショ"
+        ]
+    );
+    assert!(tokens[1].features(&tokenizer.dict).is_empty());
+    assert_eq!(
+        json::to_json(&tokens, &tokenizer.dict).unwrap(),
+        r#"[{"id":2,"class":"known","position":0,"start":0,"end":2,"surface":"辞書","features":{"pos":"名詞","pos_detail1":"一般","pos_detail2":"*","pos_detail3":"*","conjugation_type":"*","conjugation_form":"*","base_form":"辞書","reading":"ジショ","pronunciation":"ジショ"}}]"#
+    );
+
+    let token = &tokens[0];
+    let restored: Token = serde_json::from_str(&serde_json::to_string(token).unwrap()).unwrap();
+    assert_eq!(&restored, token);
+}
+
+#[test]
+fn test_upos() {
+    use crate::ud::{Tagset, Upos};
+    let ipadic = |features: &str| Tagset::Ipadic.map(&features.split(',').collect::<Vec<_>>());
+    assert_eq!(ipadic("名詞,固有名詞,地域,一般").0, Upos::Propn);
+    assert_eq!(ipadic("名詞,数,*,*"), (Upos::Num, vec!["NumType=Card"]));
+    assert_eq!(ipadic("動詞,非自立,*,*").0, Upos::Aux);
+    assert_eq!(ipadic("助詞,格助詞,一般,*").0, Upos::Adp);
+    assert_eq!(ipadic("助詞,接続助詞,*,*").0, Upos::Sconj);
+    assert_eq!(
+        ipadic("助動詞,*,*,*,特殊・ナイ,基本形,ない"),
+        (Upos::Aux, vec!["Polarity=Neg"])
+    );
+    assert_eq!(ipadic("記号,句点,*,*").0, Upos::Punct);
+    assert_eq!(ipadic("未知語").0, Upos::X);
+
+    let unidic = |features: &str| Tagset::Unidic.map(&features.split(',').collect::<Vec<_>>());
+    assert_eq!(unidic("名詞,普通名詞,一般,*").0, Upos::Noun);
+    assert_eq!(unidic("代名詞,*,*,*").0, Upos::Pron);
+    assert_eq!(unidic("形状詞,一般,*,*").0, Upos::Adj);
+    assert_eq!(unidic("補助記号,読点,*,*").0, Upos::Punct);
+    assert_eq!(unidic("接尾辞,名詞的,助数詞,*").0, Upos::Noun);
+    assert_eq!(
+        Tagset::Unidic.lemma(&["名詞", "*", "*", "*", "*", "*", "x", "*"]),
+        None
+    );
+
+    assert_eq!(Tagset::detect(&create_test_dict()), Tagset::Ipadic);
+}
+
+#[test]
+fn test_conllu() {
+    use crate::ud::{Tagset, write_conllu};
+    let tokenizer = Tokenizer::new(create_test_dict());
+    let mut buf = Vec::new();
+    write_conllu(
+        &mut buf,
+        &tokenizer.dict,
+        Tagset::Ipadic,
+        Some("1"),
+        "テスト辞書",
+        &tokenizer.tokenize("テスト辞書"),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        "# sent_id = 1\n# text = テスト辞書\n\
+         1\tテスト\tテスト\tNOUN\t名詞-一般\t_\t_\t_\t_\tSpaceAfter=No|start_char=0|end_char=3\n\
+         2\t辞書\t辞書\tNOUN\t名詞-一般\t_\t_\t_\t_\tSpaceAfter=No|start_char=3|end_char=5\n\n"
+    );
+}
+
+#[test]
+fn test_wakati() {
+    use crate::token::Token;
+    use crate::wakati::{detokenize_wakati, wakati};
+    let tokenizer = Tokenizer::new(create_test_dict());
+    assert_eq!(tokenizer.wakati("テスト辞書"), "テスト 辞書");
+    for input in ["テスト辞書", "テスト未知", ""] {
+        let tokens = tokenizer.tokenize(input);
+        assert_eq!(detokenize_wakati(&wakati(&tokens)).unwrap(), input);
+    }
+
+    // whitespace and backslashes are escaped
+    let tokens = vec![
+        Token::new(1, TokenClass::Known, 0, 0, 3, " \\t"),
+        Token::new(2, TokenClass::Known, 3, 3, 6, "テスト"),
+        Token::new(3, TokenClass::Unknown, 12, 6, 8, " \t"),
+        Token::new(4, TokenClass::Known, 14, 8, 10, "辞書"),
+        Token::new(0, TokenClass::Dummy, 20, 10, 10, "EOS"),
+    ];
+    let line = wakati(&tokens);
+    assert_eq!(line, "\\s\\\\t テスト \\s\\t 辞書");
+    assert_eq!(detokenize_wakati(&line).unwrap(), " \\tテスト \t辞書");
+    assert!(detokenize_wakati("テスト\\x").is_err());
+
+    // normalized surfaces are restored from the input
+    let tokenizer = tokenizer.with_normalizer(crate::normalize::Normalizer::new());
+    let input = "ﾃｽﾄ辞書";
+    assert_eq!(tokenizer.wakati(input), "ﾃｽﾄ 辞書");
+    assert_eq!(detokenize_wakati(&tokenizer.wakati(input)).unwrap(), input);
+}
+
+#[test]
+fn test_filters() {
+    use crate::filter::{Filters, TokenFilter, normalize_width};
+    use crate::token::Token;
+    let tokenizer = Tokenizer::new(create_test_dict());
+    let surfaces = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .filter(|t| t.class != TokenClass::Dummy)
+            .map(|t| t.surface.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let filters = Filters::new().with(TokenFilter::stop_pos(["未知語"]));
+    let tokens = tokenizer.analyze("テスト辞書あい", &filters);
+    assert_eq!(surfaces(&tokens), vec!["テスト", "辞書"]);
+    assert_eq!(tokens.last().unwrap().class, TokenClass::Dummy);
+    let filters = Filters::new().with(TokenFilter::stop_pos(["名詞,一般"]));
+    assert_eq!(
+        surfaces(&tokenizer.analyze("テスト辞書あい", &filters)),
+        vec!["あい"]
+    );
+
+    // offsets are kept while surfaces change
+    let id = tokenizer.tokenize("辞書")[0].id;
+    let tokens = vec![
+        Token::new(id, TokenClass::Known, 0, 0, 3, "じしょ"),
+        Token::new(0, TokenClass::Unknown, 9, 3, 5, "ｻｰﾊﾞｰ"),
+        Token::new(0, TokenClass::Unknown, 24, 5, 11, "ＡＢＣ１２"),
+        Token::new(0, TokenClass::Dummy, 39, 11, 11, "EOS"),
+    ];
+    let filters = Filters::new()
+        .with(TokenFilter::BaseForm)
+        .with(TokenFilter::Width)
+        .with(TokenFilter::KatakanaStem(4));
+    let filtered = filters.apply(&tokenizer.dict, tokens.clone());
+    assert_eq!(surfaces(&filtered), vec!["辞書", "サーバ", "ABC12"]);
+    assert_eq!(filtered[1].start, 3);
+    assert_eq!(filtered[3].surface, "EOS");
+    assert!(Filters::new().apply(&tokenizer.dict, tokens.clone()) == tokens);
+
+    // katakana stem requires the minimum length and katakana only
+    let stem = |surface: &str, length: usize| {
+        let tokens = vec![Token::new(0, TokenClass::Known, 0, 0, 0, surface)];
+        Filters::new()
+            .with(TokenFilter::KatakanaStem(length))
+            .apply(&tokenizer.dict, tokens)[0]
+            .surface
+            .clone()
+    };
+    assert_eq!(stem("コピー", 4), "コピー");
+    assert_eq!(stem("コピー", 3), "コピ");
+    assert_eq!(stem("すごーー", 1), "すごーー");
+
+    assert_eq!(normalize_width("ｶﾞｯﾂﾎﾟｰｽﾞ"), "ガッツポーズ");
+    assert_eq!(normalize_width("ｳﾞｧｲｵﾘﾝ"), "ヴァイオリン");
+    assert_eq!(normalize_width("ﾞｱﾞ！ｆｏｏ"), "\u{3099}ア\u{3099}!foo");
+
+    assert_eq!(
+        "stop-pos=名詞,数".parse::<TokenFilter>().unwrap(),
+        TokenFilter::StopPos(vec![vec!["名詞".to_string(), "数".to_string()]])
+    );
+    assert_eq!(
+        "katakana-stem".parse::<TokenFilter>().unwrap(),
+        TokenFilter::KatakanaStem(4)
+    );
+    assert_eq!(
+        "katakana-stem=2".parse::<TokenFilter>().unwrap(),
+        TokenFilter::KatakanaStem(2)
+    );
+    assert_eq!("width".parse::<TokenFilter>().unwrap(), TokenFilter::Width);
+    for spec in [
+        "stop-pos",
+        "stop-pos=",
+        "base-form=1",
+        "katakana-stem=x",
+        "lower",
+    ] {
+        assert!(spec.parse::<TokenFilter>().is_err(), "{}", spec);
+    }
+}
+
+#[test]
+fn test_normalize() {
+    use crate::normalize::Normalizer;
+    let normalizer = Normalizer::new();
+
+    // multi-char expansions map to the whole original character
+    let normalized = normalizer.normalize("a㍿ｶﾞｯﾂ①");
+    assert_eq!(normalized.text, "a株式会社ガッツ1");
+    assert_eq!(normalized.char_range(0, 1), (0, 1));
+    assert_eq!(normalized.char_range(1, 5), (1, 2));
+    assert_eq!(normalized.char_range(1, 3), (1, 2));
+    assert_eq!(normalized.char_range(3, 5), (1, 2));
+    // ｶﾞ is composed into ガ
+    assert_eq!(normalized.char_range(5, 6), (2, 4));
+    assert_eq!(normalized.byte_pos(5), 4);
+    assert_eq!(normalized.char_range(6, 8), (4, 6));
+    assert_eq!(normalized.char_range(8, 9), (6, 7));
+    assert_eq!(normalized.char_range(9, 9), (7, 7));
+    assert_eq!(normalized.byte_pos(9), "a㍿ｶﾞｯﾂ①".len());
+    assert_eq!(normalizer.normalize("").text, "");
+
+    let normalizer = Normalizer::new()
+        .with_keep(['～', 'ｰ'])
+        .with_replace('~', "〜");
+    assert_eq!(normalizer.normalize("ｱｰ～~Ａ").text, "アｰ～〜A");
+    // a kept character isn't composed with a following sound mark
+    assert_eq!(
+        Normalizer::new().with_keep(['ｶ']).normalize("ｶﾞ").text,
+        "ｶ\u{3099}"
+    );
+}
+
+#[test]
+fn test_tokenize_normalized() {
+    use crate::normalize::Normalizer;
+    use crate::token::Token;
+    let tokenizer = Tokenizer::new(create_test_dict()).with_normalizer(Normalizer::new());
+    let input = "ﾃｽﾄ㍿ﾃｽﾄ";
+    let expected = vec![
+        ("テスト", 0, 0, 3),
+        ("株式会社", 9, 3, 4),
+        ("テスト", 12, 4, 7),
+        ("EOS", 21, 7, 7),
+    ];
+    let summary = |tokens: &[Token]| {
+        tokens
+            .iter()
+            .map(|t| (t.surface.clone(), t.position, t.start, t.end))
+            .collect::<Vec<_>>()
+    };
+    let expected = expected
+        .into_iter()
+        .map(|(s, p, b, e)| (s.to_string(), p, b, e))
+        .collect::<Vec<_>>();
+    assert_eq!(summary(&tokenizer.tokenize(input)), expected);
+    let (tokens, _) = &tokenizer.tokenize_nbest(input, 1)[0];
+    assert_eq!(summary(tokens), expected);
+    assert_eq!(tokenizer.normalize(input), "テスト株式会社テスト");
+    assert_eq!(
+        tokenizer
+            .tokenize(input)
+            .iter()
+            .map(|t| &input[t.byte_range(input)])
+            .collect::<Vec<_>>(),
+        vec!["ﾃｽﾄ", "㍿", "ﾃｽﾄ", ""]
+    );
+
+    // without the normalizer, half-width katakana isn't in the dictionary
+    let tokenizer = Tokenizer::new(create_test_dict());
+    assert!(
+        tokenizer
+            .tokenize(input)
+            .iter()
+            .all(|t| t.surface != "テスト")
+    );
+}
+
+#[test]
+fn test_sentence_splitter() {
+    use crate::sentence::SentenceSplitter;
+    fn texts<'a>(splitter: &SentenceSplitter, document: &'a str) -> Vec<&'a str> {
+        splitter
+            .split(document)
+            .iter()
+            .map(|sentence| sentence.text)
+            .collect()
+    }
+    let splitter = SentenceSplitter::new();
+    assert_eq!(
+        texts(&splitter, "晴れた。散歩しよう！本当？？ 行く\n\nまた明日"),
+        vec!["晴れた。", "散歩しよう！", "本当？？", "行く", "また明日"]
+    );
+    // terminals inside brackets don't end a sentence
+    assert_eq!(
+        texts(
+            &splitter,
+            "彼は「行く。すぐ。」と言った。「はい。」「『いいえ。』」"
+        ),
+        vec![
+            "彼は「行く。すぐ。」と言った。",
+            "「はい。」",
+            "「『いいえ。』」"
+        ]
+    );
+    assert_eq!(
+        texts(&splitter, "「終わらない。\r\n次"),
+        vec!["「終わらない。", "次"]
+    );
+    assert!(texts(&splitter, " \n\n ").is_empty());
+    // a long run of closing brackets is scanned once
+    let document = format!("あ{}", "」".repeat(100_000));
+    assert_eq!(texts(&splitter, &document), vec![document.as_str()]);
+
+    let splitter = SentenceSplitter::new()
+        .with_terminals(['.'])
+        .with_brackets([('(', ')')]);
+    assert_eq!(
+        texts(&splitter, "A (b. c) d. E。F"),
+        vec!["A (b. c) d.", "E。F"]
+    );
+
+    let document = "  テスト。\n　辞書";
+    let sentences = SentenceSplitter::new().split(document);
+    assert_eq!(sentences.len(), 2);
+    for sentence in &sentences {
+        assert_eq!(
+            &document[sentence.position..sentence.position + sentence.text.len()],
+            sentence.text
+        );
+        assert_eq!(
+            document
+                .chars()
+                .skip(sentence.start)
+                .take(sentence.end - sentence.start)
+                .collect::<String>(),
+            sentence.text
+        );
+    }
+    assert_eq!((sentences[1].start, sentences[1].end), (8, 10));
+}
+
+#[test]
+fn test_tokenize_document() {
+    use crate::sentence::SentenceSplitter;
+    let tokenizer = Tokenizer::new(create_test_dict());
+    // the test dictionary has no entries for punctuation
+    let document = "テスト\n 辞書\n形態素";
+    let sentences = tokenizer.tokenize_document(document, &SentenceSplitter::new());
+    assert_eq!(
+        sentences
+            .iter()
+            .map(|(sentence, _)| sentence.text)
+            .collect::<Vec<_>>(),
+        vec!["テスト", "辞書", "形態素"]
+    );
+    for (sentence, tokens) in &sentences {
+        let eos = tokens.last().unwrap();
+        assert_eq!(eos.class, TokenClass::Dummy);
+        assert_eq!(eos.position, sentence.position + sentence.text.len());
+        assert_eq!(eos.start, sentence.end);
+        for token in tokens.iter().filter(|t| t.class != TokenClass::Dummy) {
+            assert_eq!(
+                &document[token.position..token.position + token.surface.len()],
+                token.surface
+            );
+            assert_eq!(token.end - token.start, token.surface.chars().count());
+        }
+    }
+    let (_, tokens) = &sentences[2];
+    assert_eq!(
+        (
+            tokens[0].surface.as_str(),
+            tokens[0].position,
+            tokens[0].start
+        ),
+        ("形態素", 18, 8)
+    );
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn test_server() {
+    use crate::server::Server;
+    use std::io::{Read, Write};
+
+    fn request(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    let tokenizer = Tokenizer::new(create_test_dict());
+    let server = Server::bind("127.0.0.1:0", 2)
+        .unwrap()
+        .with_max_nbest(1)
+        .with_max_body_size(256);
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    // a failing assertion must not wait for the server forever
+    let running = std::thread::spawn(move || server.run(&tokenizer));
+    std::thread::scope(|scope| {
+        assert_eq!(request(addr, "GET", "/health", ""), (200, "ok".to_string()));
+        let clients = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    request(
+                        addr,
+                        "POST",
+                        "/tokenize",
+                        r#"{"text":"テスト辞書","output":"wakati"}"#,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        for client in clients {
+            assert_eq!(client.join().unwrap(), (200, "テスト 辞書".to_string()));
+        }
+        let (status, body) = request(addr, "POST", "/tokenize", r#"{"text":"辞書"}"#);
+        assert_eq!(status, 200);
+        assert!(body.starts_with(r#"[{"id":2,"class":"known""#));
+        let (status, body) = request(
+            addr,
+            "POST",
+            "/tokenize",
+            r#"{"text":"テスト辞書","mode":"nbest","n":1}"#,
+        );
+        assert_eq!(status, 200);
+        let paths: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0]["cost"], 2400);
+        // n over max_nbest is rejected
+        let nbest = r#"{"text":"テスト辞書","mode":"nbest","n":2}"#;
+        assert_eq!(request(addr, "POST", "/tokenize", nbest).0, 400);
+        let nbest = r#"{"text":"テスト辞書","nbest":2}"#;
+        assert_eq!(request(addr, "POST", "/lattice", nbest).0, 400);
+        let (status, body) = request(addr, "POST", "/lattice", r#"{"text":"辞書"}"#);
+        assert_eq!(status, 200);
+        assert!(body.starts_with("graph lattice"));
+
+        assert_eq!(request(addr, "POST", "/tokenize", "{").0, 400);
+        assert_eq!(
+            request(
+                addr,
+                "POST",
+                "/tokenize",
+                r#"{"text":"辞書","output":"unknown"}"#
+            )
+            .0,
+            400
+        );
+        let text = "辞".repeat(100);
+        assert_eq!(
+            request(
+                addr,
+                "POST",
+                "/tokenize",
+                &format!(r#"{{"text":"{}"}}"#, text)
+            )
+            .0,
+            413
+        );
+        assert_eq!(request(addr, "GET", "/tokenize", "").0, 405);
+        assert_eq!(request(addr, "GET", "/", "").0, 404);
+    });
+    handle.shutdown();
+    running.join().unwrap();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_rpc() {
+    let tokenizer = Tokenizer::new(create_test_dict());
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"tokenize","params":{"text":"辞書"}}"#,
+        "",
+        r#"{"id":"a","method":"nbest","params":{"text":"テスト辞書","n":2}}"#,
+        r#"{"id":3,"method":"lookup","params":{"text":"辞書"}}"#,
+        r#"{"id":4,"method":"tokenize","params":{}}"#,
+        r#"{"id":5,"method":"parse"}"#,
+        r#"{"id":6}"#,
+        "{",
+        r#"{"jsonrpc":"1.0","id":7,"method":"tokenize","params":{"text":"辞書"}}"#,
+        r#"{"id":8,"method":"nbest","params":{"text":"辞書","n":101}}"#,
+    ]
+    .join("\n");
+    // a line that isn't UTF-8 is answered with an error and the following lines are served
+    let mut input = input.into_bytes();
+    input.extend_from_slice(b"\n\xff\xfe\n");
+    input.extend_from_slice(r#"{"id":9,"method":"tokenize","params":{"text":"辞書"}}"#.as_bytes());
+    let mut output = Vec::new();
+    crate::rpc::serve(&tokenizer, input.as_slice(), &mut output).unwrap();
+    let responses = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(responses.len(), 11);
+
+    assert_eq!(responses[0]["jsonrpc"], "2.0");
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["result"][0]["surface"], "辞書");
+    assert_eq!(responses[0]["result"][0]["features"]["reading"], "ジショ");
+    assert_eq!(responses[0]["result"].as_array().unwrap().len(), 1);
+
+    assert_eq!(responses[1]["id"], "a");
+    assert_eq!(responses[1]["result"][0]["cost"], 2400);
+    assert_eq!(responses[1]["result"][0]["tokens"][1]["surface"], "辞書");
+
+    assert_eq!(responses[2]["result"][0]["surface"], "辞書");
+    assert_eq!(responses[2]["result"][0]["class"], "known");
+    assert_eq!(responses[2]["result"][0]["cost"], 1200);
+
+    let code = |i: usize| responses[i]["error"]["code"].as_i64().unwrap();
+    assert_eq!(code(3), crate::rpc::INVALID_PARAMS as i64);
+    assert_eq!(code(4), crate::rpc::METHOD_NOT_FOUND as i64);
+    assert_eq!(code(5), crate::rpc::INVALID_REQUEST as i64);
+    assert_eq!(responses[5]["id"], 6);
+    assert_eq!(code(6), crate::rpc::PARSE_ERROR as i64);
+    assert!(responses[6]["id"].is_null());
+    assert_eq!(code(7), crate::rpc::INVALID_REQUEST as i64);
+    assert_eq!(responses[7]["id"], 7);
+    assert_eq!(code(8), crate::rpc::INVALID_PARAMS as i64);
+    assert_eq!(code(9), crate::rpc::PARSE_ERROR as i64);
+    assert!(responses[9]["id"].is_null());
+    assert_eq!(responses[10]["id"], 9);
+    assert_eq!(responses[10]["result"][0]["surface"], "辞書");
+}
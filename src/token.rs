@@ -1,6 +1,18 @@
-use kanpyo_dict::trie::da::KeywordID;
+use std::ops::Range;
+
+use kanpyo_dict::{dict::Dict, trie::da::KeywordID};
+
+use crate::lattice::node::Node;
+
+#[cfg(feature = "serde")]
+pub mod json;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum TokenClass {
     Dummy,
     Known,
@@ -8,6 +20,7 @@ pub enum TokenClass {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub id: KeywordID,
     pub class: TokenClass,
@@ -39,6 +52,26 @@ impl Token {
     pub fn length(&self) -> usize {
         self.end - self.start
     }
+
+    /// Returns the byte range of the token in `text`, the input it comes from.
+    /// Unlike `position + surface.len()`, the range holds even if the surface is normalized.
+    pub fn byte_range(&self, text: &str) -> Range<usize> {
+        let end = text
+            .get(self.position..)
+            .and_then(|rest| rest.char_indices().nth(self.length()))
+            .map_or(text.len(), |(i, _)| self.position + i);
+        self.position..end
+    }
+
+    /// Returns the features of the token in the dictionary, which are empty for BOS and EOS.
+    pub fn features<'a>(&self, dict: &'a Dict) -> Vec<&'a str> {
+        let table = match self.class {
+            TokenClass::Dummy => return Vec::new(),
+            TokenClass::Known => &dict.morph_feature_table,
+            TokenClass::Unknown => &dict.unk_dict.morph_feature_table,
+        };
+        table.features(self.id).unwrap_or_default()
+    }
 }
 
 impl PartialEq for Token {
@@ -53,3 +86,28 @@ impl PartialEq for Token {
 }
 
 impl Eq for Token {}
+
+impl From<Node> for Token {
+    fn from(node: Node) -> Self {
+        let class = match &node {
+            Node::Dummy { .. } => TokenClass::Dummy,
+            Node::Known(_) => TokenClass::Known,
+            Node::Unknown(_) => TokenClass::Unknown,
+        };
+        let surface = match &node {
+            Node::Dummy { .. } => "EOS".to_string(),
+            Node::Known(n) | Node::Unknown(n) => n.surface.clone(),
+        };
+
+        let char_pos = node.char_pos();
+        let end_pos = char_pos + surface.chars().count();
+        Token {
+            id: node.id(),
+            class,
+            position: node.byte_pos(),
+            start: char_pos,
+            end: end_pos,
+            surface,
+        }
+    }
+}
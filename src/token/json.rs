@@ -0,0 +1,87 @@
+use kanpyo_dict::dict::Dict;
+use serde::{Serialize, ser::SerializeMap};
+
+use super::Token;
+
+/// Names of the features of IPADIC.
+pub const IPADIC_FEATURE_NAMES: [&str; 9] = [
+    "pos",
+    "pos_detail1",
+    "pos_detail2",
+    "pos_detail3",
+    "conjugation_type",
+    "conjugation_form",
+    "base_form",
+    "reading",
+    "pronunciation",
+];
+
+/// NamedFeatures serializes features as an object keyed by `IPADIC_FEATURE_NAMES` in order.
+/// Features beyond the names are keyed by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedFeatures<'a>(pub Vec<&'a str>);
+
+impl Serialize for NamedFeatures<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (i, feature) in self.0.iter().enumerate() {
+            match IPADIC_FEATURE_NAMES.get(i) {
+                Some(name) => map.serialize_entry(name, feature)?,
+                None => map.serialize_entry(&i.to_string(), feature)?,
+            }
+        }
+        map.end()
+    }
+}
+
+/// JsonToken is a token with its named features in JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonToken<'a> {
+    #[serde(flatten)]
+    pub token: &'a Token,
+    pub features: NamedFeatures<'a>,
+}
+
+impl<'a> JsonToken<'a> {
+    pub fn new(token: &'a Token, dict: &'a Dict) -> Self {
+        JsonToken {
+            token,
+            features: NamedFeatures(token.features(dict)),
+        }
+    }
+}
+
+/// JsonPath is one of the n-best tokenizations with its cost in JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonPath<'a> {
+    pub cost: i32,
+    pub tokens: Vec<JsonToken<'a>>,
+}
+
+impl<'a> JsonPath<'a> {
+    /// Skips BOS and EOS like `to_json`.
+    pub fn new(tokens: &'a [Token], cost: i32, dict: &'a Dict) -> Self {
+        JsonPath {
+            cost,
+            tokens: json_tokens(tokens, dict),
+        }
+    }
+}
+
+/// Serializes tokens with their named features to a JSON array, skipping BOS and EOS.
+pub fn to_json(tokens: &[Token], dict: &Dict) -> serde_json::Result<String> {
+    serde_json::to_string(&json_tokens(tokens, dict))
+}
+
+/// Same as `to_json` but pretty-printed.
+pub fn to_json_pretty(tokens: &[Token], dict: &Dict) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&json_tokens(tokens, dict))
+}
+
+fn json_tokens<'a>(tokens: &'a [Token], dict: &'a Dict) -> Vec<JsonToken<'a>> {
+    tokens
+        .iter()
+        .filter(|token| token.class != super::TokenClass::Dummy)
+        .map(|token| JsonToken::new(token, dict))
+        .collect()
+}
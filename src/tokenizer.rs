@@ -1,46 +1,140 @@
 use crate::{
-    lattice::{self, node::Node},
+    adjustment::CostAdjustments,
+    filter::Filters,
+    lattice::{
+        self, Explanation,
+        cost::{CostModel, DictCostModel},
+    },
+    normalize::{Normalized, Normalizer},
+    sentence::{Sentence, SentenceSplitter},
     token::{Token, TokenClass},
+    wakati,
 };
 use kanpyo_dict::dict::Dict;
+use std::borrow::Cow;
 
 pub struct Tokenizer {
     pub dict: Dict,
+    /// Cost deltas applied on top of the dictionary costs.
+    pub adjustments: CostAdjustments,
+    /// Normalizer of input. Token offsets point into the input before normalization.
+    pub normalizer: Option<Normalizer>,
 }
 
 impl Tokenizer {
     pub fn new(dict: Dict) -> Self {
-        Self { dict }
+        Self {
+            dict,
+            adjustments: CostAdjustments::default(),
+            normalizer: None,
+        }
+    }
+
+    pub fn with_adjustments(mut self, adjustments: CostAdjustments) -> Self {
+        self.adjustments = adjustments;
+        self
+    }
+
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Returns `input` normalized by the normalizer if any.
+    pub fn normalize<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        match &self.normalizer {
+            Some(normalizer) => Cow::Owned(normalizer.normalize(input).text),
+            None => Cow::Borrowed(input),
+        }
+    }
+
+    // restore maps the offsets of tokens of the normalized input back to the input.
+    fn restore(normalized: Option<&Normalized>, tokens: Vec<Token>) -> Vec<Token> {
+        match normalized {
+            Some(normalized) => normalized.restore(tokens),
+            None => tokens,
+        }
+    }
+
+    /// Returns the cost model of the tokenizer, which applies the adjustments if any.
+    pub fn model(&self) -> &dyn CostModel {
+        if self.adjustments.is_empty() {
+            &DictCostModel
+        } else {
+            &self.adjustments
+        }
     }
 
     pub fn tokenize(&self, input: &str) -> Vec<Token> {
-        let lattice = lattice::Lattice::build(&self.dict, input);
+        self.tokenize_with(input, self.model())
+    }
 
-        lattice
-            .viterbi()
+    /// Splits `document` into sentences and tokenizes each of them.
+    /// Tokens of a sentence end with EOS and their offsets point into `document`.
+    pub fn tokenize_document<'a>(
+        &self,
+        document: &'a str,
+        splitter: &SentenceSplitter,
+    ) -> Vec<(Sentence<'a>, Vec<Token>)> {
+        splitter
+            .split(document)
             .into_iter()
-            .map(|node| {
-                let token_class = match &node {
-                    Node::Dummy { .. } => TokenClass::Dummy,
-                    Node::Known(_) => TokenClass::Known,
-                    Node::Unknown(_) => TokenClass::Unknown,
-                };
-                let surface = match &node {
-                    Node::Dummy { .. } => "EOS".to_string(),
-                    Node::Known(n) | Node::Unknown(n) => n.surface.clone(),
-                };
-
-                let char_pos = node.char_pos();
-                let end_pos = char_pos + surface.chars().count();
-                Token {
-                    id: node.id(),
-                    class: token_class,
-                    position: node.byte_pos(),
-                    start: char_pos,
-                    end: end_pos,
-                    surface,
+            .map(|sentence| {
+                let mut tokens = self.tokenize(sentence.text);
+                for token in &mut tokens {
+                    token.position += sentence.position;
+                    token.start += sentence.start;
+                    token.end += sentence.start;
                 }
+                (sentence, tokens)
             })
             .collect()
     }
+
+    /// Tokenizes `input` and applies the filters to the tokens.
+    pub fn analyze(&self, input: &str, filters: &Filters) -> Vec<Token> {
+        filters.apply(&self.dict, self.tokenize(input))
+    }
+
+    /// Returns up to `n` best tokenizations of `input` with their costs in ascending order of cost.
+    pub fn tokenize_nbest(&self, input: &str, n: usize) -> Vec<(Vec<Token>, i32)> {
+        let normalized = self.normalizer.as_ref().map(|n| n.normalize(input));
+        let text = normalized.as_ref().map_or(input, |n| n.text.as_str());
+        lattice::Lattice::build(&self.dict, text)
+            .nbest_with(n, self.model())
+            .into_iter()
+            .map(|(path, cost)| {
+                let tokens = path.into_iter().map(Token::from).collect();
+                (Self::restore(normalized.as_ref(), tokens), cost)
+            })
+            .collect()
+    }
+
+    /// Returns the tokens of `input` separated by a space, which `wakati::detokenize_wakati` restores.
+    /// Tokens are taken from `input` rather than normalized surfaces, so `input` is restored exactly.
+    pub fn wakati(&self, input: &str) -> String {
+        let mut tokens = self.tokenize(input);
+        for token in tokens.iter_mut().filter(|t| t.class != TokenClass::Dummy) {
+            token.surface = input[token.byte_range(input)].to_string();
+        }
+        wakati::wakati(&tokens)
+    }
+
+    /// Returns the cost breakdown of the best path of `input` from the first word to EOS.
+    /// Offsets of the nodes point into the text given by `Tokenizer::normalize`.
+    pub fn explain(&self, input: &str) -> Vec<Explanation> {
+        lattice::Lattice::build(&self.dict, &self.normalize(input)).explain_with(self.model())
+    }
+
+    /// Tokenizes `input` decoding the lattice under a cost model.
+    pub fn tokenize_with<C: CostModel + ?Sized>(&self, input: &str, model: &C) -> Vec<Token> {
+        let normalized = self.normalizer.as_ref().map(|n| n.normalize(input));
+        let text = normalized.as_ref().map_or(input, |n| n.text.as_str());
+        let tokens = lattice::Lattice::build(&self.dict, text)
+            .viterbi_with(model)
+            .into_iter()
+            .map(Token::from)
+            .collect();
+        Self::restore(normalized.as_ref(), tokens)
+    }
 }
@@ -0,0 +1,242 @@
+use std::{fmt, io::Write};
+
+use kanpyo_dict::dict::Dict;
+
+use crate::token::{Token, TokenClass};
+
+/// Upos is a universal part-of-speech tag of Universal Dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upos {
+    Adj,
+    Adp,
+    Adv,
+    Aux,
+    Cconj,
+    Det,
+    Intj,
+    Noun,
+    Num,
+    Part,
+    Pron,
+    Propn,
+    Punct,
+    Sconj,
+    Sym,
+    Verb,
+    X,
+}
+
+impl Upos {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Upos::Adj => "ADJ",
+            Upos::Adp => "ADP",
+            Upos::Adv => "ADV",
+            Upos::Aux => "AUX",
+            Upos::Cconj => "CCONJ",
+            Upos::Det => "DET",
+            Upos::Intj => "INTJ",
+            Upos::Noun => "NOUN",
+            Upos::Num => "NUM",
+            Upos::Part => "PART",
+            Upos::Pron => "PRON",
+            Upos::Propn => "PROPN",
+            Upos::Punct => "PUNCT",
+            Upos::Sconj => "SCONJ",
+            Upos::Sym => "SYM",
+            Upos::Verb => "VERB",
+            Upos::X => "X",
+        }
+    }
+}
+
+impl fmt::Display for Upos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tagset is the POS tagset of a dictionary, which decides the UPOS mapping and the lemma field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tagset {
+    /// IPADIC: `品詞,品詞細分類1,品詞細分類2,品詞細分類3,活用型,活用形,原形,読み,発音`
+    Ipadic,
+    /// UniDic: `pos1,pos2,pos3,pos4,cType,cForm,lForm,lemma,...`
+    Unidic,
+}
+
+// POS names used only by UniDic.
+const UNIDIC_POS: [&str; 6] = ["代名詞", "形状詞", "補助記号", "接頭辞", "接尾辞", "空白"];
+
+impl Tagset {
+    /// Detects the tagset from the POS names in the dictionary, which defaults to IPADIC.
+    pub fn detect(dict: &Dict) -> Self {
+        let names = &dict.morph_feature_table.name_list;
+        if names.iter().any(|name| UNIDIC_POS.contains(&name.as_str())) {
+            Tagset::Unidic
+        } else {
+            Tagset::Ipadic
+        }
+    }
+
+    fn lemma_index(&self) -> usize {
+        match self {
+            Tagset::Ipadic => 6,
+            Tagset::Unidic => 7,
+        }
+    }
+
+    /// Maps POS features to a UPOS tag and UD features like `Polarity=Neg`.
+    pub fn map(&self, features: &[&str]) -> (Upos, Vec<&'static str>) {
+        let get = |i: usize| features.get(i).copied().unwrap_or("*");
+        let upos = match self {
+            Tagset::Ipadic => ipadic_upos(get(0), get(1), get(2)),
+            Tagset::Unidic => unidic_upos(get(0), get(1), get(2)),
+        };
+        let mut feats = Vec::new();
+        if upos == Upos::Num {
+            feats.push("NumType=Card");
+        }
+        if upos == Upos::Aux && matches!(get(self.lemma_index()), "ない" | "ぬ" | "ん" | "無い")
+        {
+            feats.push("Polarity=Neg");
+        }
+        (upos, feats)
+    }
+
+    /// Returns the lemma in the features, or `None` if it's missing or `*`.
+    pub fn lemma<'a>(&self, features: &[&'a str]) -> Option<&'a str> {
+        features
+            .get(self.lemma_index())
+            .copied()
+            .filter(|lemma| *lemma != "*")
+    }
+}
+
+fn ipadic_upos(pos: &str, pos1: &str, pos2: &str) -> Upos {
+    match (pos, pos1) {
+        ("名詞", "固有名詞") => Upos::Propn,
+        ("名詞", "代名詞") => Upos::Pron,
+        ("名詞", "数") => Upos::Num,
+        ("名詞", "形容動詞語幹" | "ナイ形容詞語幹") => Upos::Adj,
+        ("名詞", "特殊") if pos2 == "助動詞語幹" => Upos::Aux,
+        ("名詞", "接続詞的") => Upos::Cconj,
+        ("名詞" | "接頭詞", _) => Upos::Noun,
+        ("動詞", "自立") => Upos::Verb,
+        ("動詞", _) => Upos::Aux,
+        ("形容詞", "非自立") => Upos::Aux,
+        ("形容詞", _) => Upos::Adj,
+        ("副詞", _) => Upos::Adv,
+        ("連体詞", _) => Upos::Det,
+        ("接続詞", _) => Upos::Cconj,
+        ("感動詞" | "フィラー", _) => Upos::Intj,
+        ("助詞", "接続助詞") => Upos::Sconj,
+        ("助詞", "並立助詞") => Upos::Cconj,
+        ("助詞", "終助詞" | "副助詞／並立助詞／終助詞") => Upos::Part,
+        ("助詞", _) => Upos::Adp,
+        ("助動詞", _) => Upos::Aux,
+        ("記号", "句点" | "読点" | "括弧開" | "括弧閉") => Upos::Punct,
+        ("記号", _) => Upos::Sym,
+        ("その他", "間投") => Upos::Intj,
+        _ => Upos::X,
+    }
+}
+
+fn unidic_upos(pos: &str, pos1: &str, pos2: &str) -> Upos {
+    match (pos, pos1) {
+        ("名詞", "固有名詞") => Upos::Propn,
+        ("名詞", "数詞") => Upos::Num,
+        ("名詞", "助動詞語幹") => Upos::Aux,
+        ("名詞" | "接頭辞", _) => Upos::Noun,
+        ("代名詞", _) => Upos::Pron,
+        ("形状詞", "助動詞語幹") => Upos::Aux,
+        ("形状詞" | "形容詞", _) => Upos::Adj,
+        ("連体詞", _) => Upos::Det,
+        ("副詞", _) => Upos::Adv,
+        ("接続詞", _) => Upos::Cconj,
+        ("感動詞", _) => Upos::Intj,
+        ("動詞", _) => Upos::Verb,
+        ("助動詞", _) => Upos::Aux,
+        ("助詞", "接続助詞" | "準体助詞") => Upos::Sconj,
+        ("助詞", "終助詞") => Upos::Part,
+        ("助詞", _) => Upos::Adp,
+        ("接尾辞", "形容詞的" | "形状詞的") => Upos::Adj,
+        ("接尾辞", "動詞的") => Upos::Verb,
+        ("接尾辞", _) => Upos::Noun,
+        ("補助記号", "ＡＡ") => Upos::Sym,
+        ("補助記号", _) => Upos::Punct,
+        ("記号" | "空白", _) => Upos::Sym,
+        _ if pos2 == "数詞" => Upos::Num,
+        _ => Upos::X,
+    }
+}
+
+// xpos joins the POS fields except `*`, e.g. `名詞-固有名詞-地域-一般`.
+fn xpos(features: &[&str]) -> String {
+    features
+        .iter()
+        .take(4)
+        .filter(|pos| **pos != "*" && !pos.is_empty())
+        .copied()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Writes tokens of `text` as a CoNLL-U sentence block.
+///
+/// LEMMA is the base form, XPOS is the raw POS and MISC has `SpaceAfter=No` and the char offsets
+/// of the token in `text` (`start_char` and `end_char`). Whitespace tokens are not written but
+/// drop `SpaceAfter=No` of the previous token. HEAD and DEPREL are left unspecified.
+pub fn write_conllu<W: Write>(
+    w: &mut W,
+    dict: &Dict,
+    tagset: Tagset,
+    sent_id: Option<&str>,
+    text: &str,
+    tokens: &[Token],
+) -> std::io::Result<()> {
+    if let Some(sent_id) = sent_id {
+        writeln!(w, "# sent_id = {}", sent_id)?;
+    }
+    writeln!(w, "# text = {}", text)?;
+    let words = tokens
+        .iter()
+        .filter(|token| token.class != TokenClass::Dummy)
+        .collect::<Vec<_>>();
+    let mut id = 0;
+    for (i, token) in words.iter().enumerate() {
+        if token.surface.trim().is_empty() {
+            continue;
+        }
+        id += 1;
+        let features = token.features(dict);
+        let (upos, feats) = tagset.map(&features);
+        let lemma = tagset.lemma(&features).unwrap_or(&token.surface);
+        let xpos = xpos(&features);
+        let space_after = words
+            .get(i + 1)
+            .is_some_and(|next| next.surface.starts_with(char::is_whitespace));
+        let mut misc = Vec::new();
+        if !space_after {
+            misc.push("SpaceAfter=No".to_string());
+        }
+        misc.push(format!("start_char={}", token.start));
+        misc.push(format!("end_char={}", token.end));
+        writeln!(
+            w,
+            "{}\t{}\t{}\t{}\t{}\t{}\t_\t_\t_\t{}",
+            id,
+            token.surface,
+            lemma,
+            upos,
+            if xpos.is_empty() { "_" } else { &xpos },
+            if feats.is_empty() {
+                "_".to_string()
+            } else {
+                feats.join("|")
+            },
+            misc.join("|")
+        )?;
+    }
+    writeln!(w)
+}
@@ -0,0 +1,68 @@
+use kanpyo_dict::error::{KanpyoError, Result};
+
+use crate::token::{Token, TokenClass};
+
+// escape escapes whitespace and backslashes in a surface so that a space only separates tokens.
+fn escape(surface: &str, out: &mut String) {
+    for ch in surface.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            ' ' => out.push_str("\\s"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+fn unescape(word: &str, out: &mut String) -> Result<()> {
+    let mut chars = word.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('s') => out.push(' '),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            _ => {
+                return Err(KanpyoError::Parse(format!(
+                    "invalid escape sequence in {:?}",
+                    word
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Joins the surfaces of tokens with a space, which `detokenize_wakati` restores exactly.
+/// Whitespace and backslashes in surfaces are escaped as `\s`, `\t`, `\n`, `\r` and `\\`,
+/// so original spaces are kept as tokens like `東京 \s 都`.
+/// The restored text is the input only if surfaces are unchanged, i.e. without a normalizer or filters.
+pub fn wakati(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens
+        .iter()
+        .filter(|token| token.class != TokenClass::Dummy)
+    {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        escape(&token.surface, &mut out);
+    }
+    out
+}
+
+/// Restores the original text from a line written by `wakati`.
+pub fn detokenize_wakati(line: &str) -> Result<String> {
+    let mut out = String::with_capacity(line.len());
+    for word in line.split(' ') {
+        unescape(word, &mut out)?;
+    }
+    Ok(out)
+}